@@ -1,29 +1,204 @@
 use anyhow::Result;
-use std::{collections::HashMap, iter::zip, path::PathBuf};
+use std::{
+    collections::HashMap,
+    iter::zip,
+    path::{Path, PathBuf},
+};
 
-use crate::util;
+use crate::util::{self, AocError, Part, Solution};
 
-pub fn run() -> Result<()> {
-    println!("day 01");
-    let path = PathBuf::from("./resources/day01.txt");
-    let [mut left, mut right] = util::get_data_fixed_columns(&path)?;
+fn total_distance(left: &[usize], right: &[usize]) -> usize {
+    let mut left = left.to_vec();
+    let mut right = right.to_vec();
+    left.sort();
+    right.sort();
+
+    zip(&left, &right).map(|(a, b)| a.abs_diff(*b)).sum()
+}
+
+#[allow(dead_code)]
+fn distance_between_columns(cols: &[Vec<usize>], a: usize, b: usize) -> Result<usize> {
+    let (Some(left), Some(right)) = (cols.get(a), cols.get(b)) else {
+        let msg = format!("columns {a} and {b} not within {} columns", cols.len());
+        return Err(AocError::ValueError(msg).into());
+    };
+    Ok(total_distance(left, right))
+}
+
+fn count_frequencies(values: &[usize]) -> HashMap<usize, usize> {
+    let mut freq = HashMap::new();
+    for &val in values.iter() {
+        *freq.entry(val).or_insert(0usize) += 1;
+    }
+    freq
+}
+
+fn similarity_from_frequencies(left: &[usize], right_freq: &HashMap<usize, usize>) -> usize {
+    left.iter()
+        .map(|val| val * right_freq.get(val).copied().unwrap_or(0))
+        .sum()
+}
 
+fn similarity_score(left: &[usize], right: &[usize]) -> usize {
+    let right_freq = count_frequencies(right);
+    similarity_from_frequencies(left, &right_freq)
+}
+
+fn check_same_length(left: &[usize], right: &[usize]) -> Result<()> {
+    if left.len() != right.len() {
+        let msg = format!("lists differ in length: {} vs {}", left.len(), right.len());
+        return Err(AocError::ValueError(msg).into());
+    }
+    Ok(())
+}
+
+fn solve(mut left: Vec<usize>, mut right: Vec<usize>) -> Result<(usize, usize)> {
+    check_same_length(&left, &right)?;
     left.sort();
     right.sort();
 
-    let distance: usize = zip(&mut *left, &mut *right)
-        .map(|(a, b)| a.abs_diff(*b))
-        .sum();
-    println!("distance: {distance}");
+    // both lists are sorted, so the matches in right for each left value
+    // are found by walking right alongside left
+    let mut distance = 0;
+    let mut similarity = 0;
+    let mut right_index = 0;
+    let mut matches = None;
+    for (index, &a) in left.iter().enumerate() {
+        distance += a.abs_diff(right[index]);
+
+        let count = match matches {
+            Some((value, count)) if value == a => count,
+            _ => {
+                while right_index < right.len() && right[right_index] < a {
+                    right_index += 1;
+                }
+                let mut count = 0;
+                while right_index < right.len() && right[right_index] == a {
+                    count += 1;
+                    right_index += 1;
+                }
+                matches = Some((a, count));
+                count
+            }
+        };
+        similarity += a * count;
+    }
+    Ok((distance, similarity))
+}
+
+pub fn run(part: Part) -> Result<()> {
+    println!("day 01");
+    let path = PathBuf::from("./resources/day01.txt");
+    let [left, right] = util::get_data_fixed_columns(&path)?;
+    check_same_length(&left, &right)?;
+
+    let (distance, similarity) = match part {
+        Part::Both => {
+            let (distance, similarity) = solve(left, right)?;
+            (Some(distance), Some(similarity))
+        }
+        Part::One => (Some(total_distance(&left, &right)), None),
+        Part::Two => (None, Some(similarity_score(&left, &right))),
+    };
 
-    let mut right_freq = HashMap::new();
-    for val in right.iter() {
-        *right_freq.entry(val).or_insert(0usize) += 1;
+    if let Some(distance) = distance {
+        println!("distance: {distance}");
+    }
+
+    if let Some(similarity) = similarity {
+        println!("similarity: {similarity}");
     }
-    let similarity: usize = left
-        .iter()
-        .map(|val| val * *right_freq.entry(val).or_default())
-        .sum();
-    println!("similarity: {similarity}");
     Ok(())
 }
+
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        1
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let [left, right] = util::get_data_fixed_columns(path)?;
+        check_same_length(&left, &right)?;
+        Ok(total_distance(&left, &right).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let [left, right] = util::get_data_fixed_columns(path)?;
+        check_same_length(&left, &right)?;
+        Ok(similarity_score(&left, &right).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_distance() {
+        let left = vec![3, 4, 2, 1, 3, 3];
+        let right = vec![4, 3, 5, 3, 9, 3];
+        let distance = total_distance(&left, &right);
+        assert_eq!(distance, 11);
+        assert_eq!(left, vec![3, 4, 2, 1, 3, 3]);
+    }
+
+    #[test]
+    fn test_example_similarity() {
+        let left = vec![3, 4, 2, 1, 3, 3];
+        let right = vec![4, 3, 5, 3, 9, 3];
+        let similarity = similarity_score(&left, &right);
+        assert_eq!(similarity, 31);
+    }
+
+    #[test]
+    fn test_similarity_left_only_value() {
+        let left = vec![1, 7];
+        let right = vec![1, 1, 2];
+        let right_freq = count_frequencies(&right);
+        assert_eq!(right_freq.len(), 2);
+        let similarity = similarity_from_frequencies(&left, &right_freq);
+        assert_eq!(similarity, 2);
+        assert_eq!(right_freq.len(), 2);
+        assert!(!right_freq.contains_key(&7));
+    }
+
+    #[test]
+    fn test_example_solve() {
+        let left = vec![3, 4, 2, 1, 3, 3];
+        let right = vec![4, 3, 5, 3, 9, 3];
+        let (distance, similarity) = solve(left, right).unwrap();
+        assert_eq!(distance, 11);
+        assert_eq!(similarity, 31);
+    }
+
+    #[test]
+    fn test_solve_repeated_values() {
+        let left = vec![5, 1, 5, 2, 9, 5];
+        let right = vec![5, 5, 0, 9, 1, 7];
+        let (distance, similarity) = solve(left.clone(), right.clone()).unwrap();
+        assert_eq!(distance, total_distance(&left, &right));
+        assert_eq!(similarity, similarity_score(&left, &right));
+        assert_eq!(similarity, 5 * 2 * 3 + 1 + 9);
+    }
+
+    #[test]
+    fn test_solve_differing_lengths() {
+        let left = vec![3, 4, 2];
+        let right = vec![4, 3];
+        assert!(solve(left, right).is_err());
+    }
+
+    #[test]
+    fn test_distance_between_columns() {
+        let cols = vec![
+            vec![3, 4, 2, 1, 3, 3],
+            vec![0, 0, 0, 0, 0, 0],
+            vec![4, 3, 5, 3, 9, 3],
+        ];
+        assert_eq!(distance_between_columns(&cols, 0, 2).unwrap(), 11);
+        assert_eq!(distance_between_columns(&cols, 1, 0).unwrap(), 16);
+        assert!(distance_between_columns(&cols, 0, 3).is_err());
+    }
+}
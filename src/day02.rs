@@ -1,13 +1,15 @@
 use anyhow::Result;
 use std::{
-    cmp::{max, min},
+    cmp::min,
     ops::BitAnd,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use crate::util;
+use crate::util::{self, Part, Solution};
 
-fn validate_record_dampened(row: &[usize], skips: usize) -> bool {
+const MAX_STEP: u64 = 3;
+
+fn validate_record_dampened(row: &[i64], skips: usize, max_step: u64) -> bool {
     let skips = min(row.len() - 1, skips);
 
     // dynamic program: resolve recursive evaluation from the back
@@ -15,7 +17,7 @@ fn validate_record_dampened(row: &[usize], skips: usize) -> bool {
     let mut increasing_table = vec![vec![false; row.len()]; skips + 1];
     let mut decreasing_table = vec![vec![false; row.len()]; skips + 1];
 
-    let gradual = |a, b| (max(a, b) - min(a, b)) <= 3;
+    let gradual = |a: i64, b: i64| a.abs_diff(b) <= max_step;
     let increasing = |a, b| (a < b) && gradual(a, b);
     let decreasing = |a, b| (a > b) && gradual(a, b);
 
@@ -53,39 +55,63 @@ fn validate_record_dampened(row: &[usize], skips: usize) -> bool {
 
     increasing_table[skips][0] || decreasing_table[skips][0]
 }
-pub fn run() -> Result<()> {
+
+// already safe records report 0, as dropping the first level never breaks them
+#[allow(dead_code)]
+fn safe_removal_index(row: &[i64]) -> Option<usize> {
+    (0..row.len()).find(|&removed| {
+        let reduced = row
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != removed)
+            .map(|(_, &v)| v)
+            .collect::<Vec<_>>();
+        reduced.is_empty() || validate_record_dampened(&reduced, 0, MAX_STEP)
+    })
+}
+
+fn count_safe(data: &[Vec<i64>], skips: usize) -> usize {
+    data.iter()
+        .filter(|row| validate_record_dampened(row, skips, MAX_STEP))
+        .count()
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 02");
     let path = PathBuf::from("./resources/day02.txt");
     let data = util::get_data_rows(&path)?;
 
-    let increasing = |a, b| a < b;
-    let decreasing = |a, b| a > b;
-    let gradual = |a, b| (max(a, b) - min(a, b)) <= 3;
-
-    let valid_records = data
-        .iter()
-        .filter(|row| {
-            row.windows(2)
-                .flat_map(<&[usize; 2]>::try_from)
-                .all(|&[a, b]| increasing(a, b) && gradual(a, b))
-                || row
-                    .windows(2)
-                    .flat_map(<&[usize; 2]>::try_from)
-                    .all(|&[a, b]| decreasing(a, b) && gradual(a, b))
-        })
-        .count();
-    println!("valid records: {valid_records}");
-
-    let dampened_records: Vec<_> = data
-        .iter()
-        .filter(|&row| validate_record_dampened(row, 1))
-        .collect();
-    let dampened_valid = dampened_records.len();
-    println!("valid dampened records: {dampened_valid}");
+    if part.one() {
+        let valid_records = count_safe(&data, 0);
+        println!("valid records: {valid_records}");
+    }
+
+    if part.two() {
+        let dampened_valid = count_safe(&data, 1);
+        println!("valid dampened records: {dampened_valid}");
+    }
 
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        2
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_rows(path)?;
+        Ok(count_safe(&data, 0).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_rows(path)?;
+        Ok(count_safe(&data, 1).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,84 +119,124 @@ mod tests {
     #[test]
     fn test_one() {
         let row = vec![1; 1];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_small() {
         let row = vec![50, 51];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_incr() {
         let row = vec![50, 51, 52, 53, 54];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_incr_skip_first() {
         let row = vec![52, 51, 52, 53, 54];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_incr_skip_last() {
         let row = vec![50, 51, 52, 53, 52];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_incr_skip_middle() {
         let row = vec![50, 51, 46, 53, 54];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_incr_skip_twice() {
         let row = vec![50, 51, 51, 53, 53];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(!valid);
     }
 
     #[test]
     fn test_decr() {
         let row = vec![66, 63, 59, 58, 56];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(!valid);
     }
 
     #[test]
     fn test_decr_skip_first() {
         let row = vec![68, 61, 60, 59, 56];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_decr_skip_last() {
         let row = vec![62, 61, 60, 59, 52];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_decr_skip_middle() {
         let row = vec![38, 34, 37, 35, 34];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(valid);
     }
 
     #[test]
     fn test_decr_skip_twice() {
         let row = vec![62, 62, 60, 60, 59];
-        let valid = validate_record_dampened(&row, 1);
+        let valid = validate_record_dampened(&row, 1, MAX_STEP);
         assert!(!valid);
     }
+
+    #[test]
+    fn test_example_count_safe() {
+        let data = vec![
+            vec![7, 6, 4, 2, 1],
+            vec![1, 2, 7, 8, 9],
+            vec![9, 7, 6, 2, 1],
+            vec![1, 3, 2, 4, 5],
+            vec![8, 6, 4, 4, 1],
+            vec![1, 3, 6, 7, 9],
+        ];
+        assert_eq!(count_safe(&data, 0), 2);
+        assert_eq!(count_safe(&data, 1), 4);
+    }
+
+    #[test]
+    fn test_removal_index() {
+        assert_eq!(safe_removal_index(&[50, 51, 52, 53, 54]), Some(0));
+        assert_eq!(safe_removal_index(&[52, 51, 52, 53, 54]), Some(0));
+        assert_eq!(safe_removal_index(&[50, 51, 46, 53, 54]), Some(2));
+        assert_eq!(safe_removal_index(&[50, 51, 52, 53, 52]), Some(4));
+        assert_eq!(safe_removal_index(&[50, 51, 51, 53, 53]), None);
+        assert_eq!(safe_removal_index(&[1]), Some(0));
+    }
+
+    #[test]
+    fn test_larger_max_step() {
+        let row = vec![1, 6, 11];
+        assert!(!validate_record_dampened(&row, 0, MAX_STEP));
+        assert!(validate_record_dampened(&row, 0, 5));
+    }
+
+    #[test]
+    fn test_negative_levels() {
+        let row = vec![-2, 0, 3, 5];
+        assert!(validate_record_dampened(&row, 0, MAX_STEP));
+        let row = vec![3, -1, -2];
+        assert!(!validate_record_dampened(&row, 0, MAX_STEP));
+        assert!(validate_record_dampened(&row, 1, MAX_STEP));
+    }
 }
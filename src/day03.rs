@@ -1,38 +1,67 @@
 use anyhow::Result;
-use std::{cmp::min, path::PathBuf};
+use std::{
+    cmp::min,
+    path::{Path, PathBuf},
+};
 
-use crate::util;
+use crate::util::{self, Part, Solution};
 
+#[derive(Debug, PartialEq, Eq)]
 struct Mul(usize, usize);
 
-fn parse_mul(input: &str) -> Vec<Mul> {
+struct MulConfig<'a> {
+    keyword: &'a str,
+    max_digits: usize,
+}
+
+const DEFAULT_CONFIG: MulConfig<'static> = MulConfig {
+    keyword: "mul",
+    max_digits: 3,
+};
+
+// returns the byte range of every match together with the parsed mul
+fn parse_mul_spans_with(input: &str, config: &MulConfig) -> Vec<(usize, usize, Mul)> {
     let mut result = Vec::new();
     let mut remaining = input;
-    const MAX_PATTERN: usize = 12;
-
-    fn parse_val(s: &str) -> Option<usize> {
-        if s.len() > 3 {
+    let mut offset = 0;
+
+    let MulConfig {
+        keyword,
+        max_digits,
+    } = *config;
+    let opening = format!("{keyword}(");
+    // keyword, opening parenthesis, two values, comma and closing parenthesis
+    let max_pattern = opening.len() + 2 * max_digits + 2;
+
+    let parse_val = |s: &str| -> Option<usize> {
+        if s.len() > max_digits {
             None
         } else {
             s.parse().ok()
         }
-    }
+    };
 
     while !remaining.is_empty() {
-        let Some(start_index) = remaining.find("mul(") else {
+        let Some(start_index) = remaining.find(&opening) else {
             break;
         };
         // skip forward for next iteration, but still check current mul
         let check_slice = &remaining[start_index..];
-        remaining = &remaining[start_index + 4..];
-
-        let slice_end = min(MAX_PATTERN, check_slice.len());
+        let start = offset + start_index;
+        remaining = &remaining[start_index + opening.len()..];
+        offset += start_index + opening.len();
+
+        let mut slice_end = min(max_pattern, check_slice.len());
+        // never cut a multibyte character in half
+        while !check_slice.is_char_boundary(slice_end) {
+            slice_end -= 1;
+        }
         let check_slice = &check_slice[..slice_end];
 
         let Some(comma) = check_slice.find(",") else {
             continue;
         };
-        let Some(first_val) = parse_val(&check_slice[4..comma]) else {
+        let Some(first_val) = parse_val(&check_slice[opening.len()..comma]) else {
             continue;
         };
 
@@ -44,61 +73,131 @@ fn parse_mul(input: &str) -> Vec<Mul> {
         let Some(second_val) = parse_val(&check_slice[..closing]) else {
             continue;
         };
-        result.push(Mul(first_val, second_val));
+        let end = start + comma + 1 + closing + 1;
+        result.push((start, end, Mul(first_val, second_val)));
     }
 
     result
 }
 
-fn parse_mul_conditional(input: &str) -> Vec<Mul> {
+#[allow(dead_code)]
+fn parse_mul_spans(input: &str) -> Vec<(usize, usize, Mul)> {
+    parse_mul_spans_with(input, &DEFAULT_CONFIG)
+}
+
+fn parse_mul_with(input: &str, config: &MulConfig) -> Vec<Mul> {
+    parse_mul_spans_with(input, config)
+        .into_iter()
+        .map(|(_, _, mul)| mul)
+        .collect()
+}
+
+fn parse_mul(input: &str) -> Vec<Mul> {
+    parse_mul_with(input, &DEFAULT_CONFIG)
+}
+
+// splits the input into slices that are either enabled or disabled
+fn conditional_regions(input: &str) -> Vec<(bool, &str)> {
     let mut result = Vec::new();
     let mut remaining = input;
 
     while !remaining.is_empty() {
         // we are always enabled at the beginning of a loop
         let Some(dont_index) = remaining.find("don't()") else {
-            result.extend(parse_mul(remaining));
+            result.push((true, remaining));
             break;
         };
-        let mul_slice = &remaining[..dont_index];
-        result.extend(parse_mul(mul_slice));
+        result.push((true, &remaining[..dont_index]));
 
         // skip forward until we are enabled again
         let searching_do = &remaining[dont_index + 7..];
         let Some(do_index) = searching_do.find("do()") else {
+            result.push((false, searching_do));
             break;
         };
-        remaining = &remaining[dont_index + 7 + do_index + 4..];
+        result.push((false, &searching_do[..do_index]));
+        remaining = &searching_do[do_index + 4..];
     }
 
     result
 }
 
-pub fn run() -> Result<()> {
+fn parse_mul_conditional(input: &str) -> Vec<Mul> {
+    conditional_regions(input)
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .flat_map(|(_, slice)| parse_mul(slice))
+        .collect()
+}
+
+#[allow(dead_code)]
+fn sum_enabled_and_disabled(input: &str) -> (usize, usize) {
+    let mut enabled_sum = 0;
+    let mut disabled_sum = 0;
+    for (enabled, slice) in conditional_regions(input) {
+        let sum = parse_mul(slice)
+            .iter()
+            .map(|&Mul(a, b)| a * b)
+            .sum::<usize>();
+        match enabled {
+            true => enabled_sum += sum,
+            false => disabled_sum += sum,
+        }
+    }
+    (enabled_sum, disabled_sum)
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 03");
     let path = PathBuf::from("./resources/day03.txt");
     let data = util::get_data_string(&path)?;
-    let muls = parse_mul(&data);
-
-    let result = muls
-        .iter()
-        .map(|&Mul(a, b)| a * b)
-        .reduce(|a, b| a + b)
-        .unwrap_or(0);
-    println!("sum of mul's: {result}");
 
-    let cond_muls = parse_mul_conditional(&data);
+    if part.one() {
+        let muls = parse_mul(&data);
+        let result = muls
+            .iter()
+            .map(|&Mul(a, b)| a * b)
+            .reduce(|a, b| a + b)
+            .unwrap_or(0);
+        println!("sum of mul's: {result}");
+    }
 
-    let cond_result = cond_muls
-        .iter()
-        .map(|&Mul(a, b)| a * b)
-        .reduce(|a, b| a + b)
-        .unwrap_or(0);
-    println!("mul's respecting conditions: {cond_result}");
+    if part.two() {
+        let cond_muls = parse_mul_conditional(&data);
+        let cond_result = cond_muls
+            .iter()
+            .map(|&Mul(a, b)| a * b)
+            .reduce(|a, b| a + b)
+            .unwrap_or(0);
+        println!("mul's respecting conditions: {cond_result}");
+    }
 
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        3
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let result: usize = parse_mul(&data).iter().map(|&Mul(a, b)| a * b).sum();
+        Ok(result.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let result: usize = parse_mul_conditional(&data)
+            .iter()
+            .map(|&Mul(a, b)| a * b)
+            .sum();
+        Ok(result.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,10 +209,47 @@ mod tests {
         assert_eq!(muls.len(), 2);
     }
 
+    #[test]
+    fn test_parse_spans() {
+        let s = "x mul(1,2)y mul(3,4)";
+        let spans = parse_mul_spans(s);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1], (12, 20, Mul(3, 4)));
+        assert_eq!(&s[spans[0].0..spans[0].1], "mul(1,2)");
+    }
+
+    #[test]
+    fn test_parse_multibyte() {
+        let s = "mul(111,2🎄)mul(3,4)🎄mul(5,6)";
+        let muls = parse_mul(s);
+        assert_eq!(muls, vec![Mul(3, 4), Mul(5, 6)]);
+    }
+
+    #[test]
+    fn test_parse_custom_config() {
+        let config = MulConfig {
+            keyword: "mult",
+            max_digits: 4,
+        };
+        let s = "mul(1,2) mult(1234,5) mult(12345,6)";
+        let muls = parse_mul_with(s, &config);
+        assert_eq!(muls, vec![Mul(1234, 5)]);
+        let muls = parse_mul(s);
+        assert_eq!(muls, vec![Mul(1, 2)]);
+    }
+
     #[test]
     fn test_parse_cond() {
         let s = "a don't() mul(1,2) to do() mul(43,654)";
         let muls = parse_mul_conditional(s);
         assert_eq!(muls.len(), 1);
     }
+
+    #[test]
+    fn test_enabled_and_disabled() {
+        let s = "mul(2,2)don't()mul(3,3)do()mul(4,4)";
+        assert_eq!(sum_enabled_and_disabled(s), (20, 9));
+        let s = "mul(2,2)don't()mul(3,3)";
+        assert_eq!(sum_enabled_and_disabled(s), (4, 9));
+    }
 }
@@ -1,16 +1,22 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::util;
+use crate::util::{self, AocError, Part, Solution};
+
+// pattern value that matches any cell
+const WILDCARD: u8 = u8::MAX;
 
 fn mapping_fn(c: char, mapping: &str) -> u8 {
+    if c == '?' {
+        return WILDCARD;
+    }
     mapping
         .find(c)
         .map(|i| i as u8)
         .unwrap_or(mapping.len() as u8)
 }
 
-fn convert_to_vec_of_vecs(input: &str, mapping: &str) -> Vec<Vec<u8>> {
+fn convert_to_vec_of_vecs(input: &str, mapping: &str) -> Result<Vec<Vec<u8>>, AocError> {
     let res = input
         .split("\n")
         .filter(|s| !s.is_empty())
@@ -21,19 +27,38 @@ fn convert_to_vec_of_vecs(input: &str, mapping: &str) -> Vec<Vec<u8>> {
         })
         .collect::<Vec<_>>();
 
-    // make sure each inner vector has the same length
-    if !res.is_empty() {
-        let length = res[0].len();
-        res.iter().skip(1).for_each(|v| assert_eq!(v.len(), length));
+    // rows may differ in length, but there has to be something to search
+    if res.is_empty() {
+        return Err(AocError::ParseError);
     }
-    res
+    Ok(res)
+}
+
+// out of range cells are treated as not matching anything
+fn cell(data: &[Vec<u8>], x: usize, y: usize) -> Option<u8> {
+    data.get(y)?.get(x).copied()
+}
+
+fn collect_cells<I>(data: &[Vec<u8>], coords: I) -> Option<Vec<u8>>
+where
+    I: Iterator<Item = (usize, usize)>,
+{
+    coords.map(|(x, y)| cell(data, x, y)).collect()
+}
+
+fn matches_pattern(container: &[u8], pattern: &[u8]) -> bool {
+    container.len() == pattern.len()
+        && container
+            .iter()
+            .zip(pattern)
+            .all(|(&c, &p)| p == WILDCARD || c == p)
 }
 
 fn cmp_fwd_bwd(mut container: Vec<u8>, pattern: &[u8]) -> usize {
     let mut total = 0;
-    total += (container == pattern) as usize;
+    total += matches_pattern(&container, pattern) as usize;
     container.reverse();
-    total += (container == pattern) as usize;
+    total += matches_pattern(&container, pattern) as usize;
     total
 }
 
@@ -47,60 +72,51 @@ fn cmp_x_diagonals(mut diag1: Vec<u8>, mut diag2: Vec<u8>, pattern: &[u8]) -> us
         std::mem::swap(&mut diag2[0], &mut diag1[last]);
     }
 
+    let both_match = |diag1: &[u8], diag2: &[u8]| {
+        matches_pattern(diag1, pattern) && matches_pattern(diag2, pattern)
+    };
+
     let mut total = 0;
-    total += (diag1 == pattern && diag2 == pattern) as usize;
+    total += both_match(&diag1, &diag2) as usize;
     rotate(&mut diag1, &mut diag2);
-    total += (diag1 == pattern && diag2 == pattern) as usize;
+    total += both_match(&diag1, &diag2) as usize;
     rotate(&mut diag1, &mut diag2);
-    total += (diag1 == pattern && diag2 == pattern) as usize;
+    total += both_match(&diag1, &diag2) as usize;
     rotate(&mut diag1, &mut diag2);
-    total += (diag1 == pattern && diag2 == pattern) as usize;
+    total += both_match(&diag1, &diag2) as usize;
     total
 }
 
 fn find_pattern_1d(x: usize, y: usize, data: &[Vec<u8>], pattern: &[u8]) -> usize {
     let mut total = 0;
+    let len = pattern.len();
 
     // horizontal
-    if x + pattern.len() <= data[0].len() {
-        let horizontal = data[y][x..x + pattern.len()].to_vec();
+    if let Some(horizontal) = collect_cells(data, (0..len).map(|i| (x + i, y))) {
         total += cmp_fwd_bwd(horizontal, pattern);
     }
     //vertical
-    if y + pattern.len() <= data.len() {
-        let mut vertical = Vec::with_capacity(pattern.len());
-        for i in 0..pattern.len() {
-            vertical.push(data[y + i][x]);
-        }
+    if let Some(vertical) = collect_cells(data, (0..len).map(|i| (x, y + i))) {
         total += cmp_fwd_bwd(vertical, pattern);
     }
 
-    if x + pattern.len() <= data[0].len() && y + pattern.len() <= data.len() {
-        let mut diagonal_1 = Vec::with_capacity(pattern.len());
-        let mut diagonal_2 = Vec::with_capacity(pattern.len());
-        for i in 0..pattern.len() {
-            diagonal_1.push(data[y + i][x + i]);
-            diagonal_2.push(data[y + i][x + pattern.len() - 1 - i]);
-        }
+    if let Some(diagonal_1) = collect_cells(data, (0..len).map(|i| (x + i, y + i))) {
         total += cmp_fwd_bwd(diagonal_1, pattern);
+    }
+    if let Some(diagonal_2) = collect_cells(data, (0..len).map(|i| (x + len - 1 - i, y + i))) {
         total += cmp_fwd_bwd(diagonal_2, pattern);
     }
     total
 }
 
 fn find_pattern_2d(x: usize, y: usize, data: &[Vec<u8>], pattern: &[u8]) -> usize {
-    let mut total = 0;
-
-    if x + pattern.len() <= data[0].len() && y + pattern.len() <= data.len() {
-        let mut diagonal_1 = Vec::with_capacity(pattern.len());
-        let mut diagonal_2 = Vec::with_capacity(pattern.len());
-        for i in 0..pattern.len() {
-            diagonal_1.push(data[y + i][x + i]);
-            diagonal_2.push(data[y + i][x + pattern.len() - 1 - i]);
-        }
-        total += cmp_x_diagonals(diagonal_1, diagonal_2, pattern);
+    let len = pattern.len();
+    let diagonal_1 = collect_cells(data, (0..len).map(|i| (x + i, y + i)));
+    let diagonal_2 = collect_cells(data, (0..len).map(|i| (x + len - 1 - i, y + i)));
+    match (diagonal_1, diagonal_2) {
+        (Some(diagonal_1), Some(diagonal_2)) => cmp_x_diagonals(diagonal_1, diagonal_2, pattern),
+        _ => 0,
     }
-    total
 }
 
 fn find_patterns<'a, F>(data: &'a [Vec<u8>], find_pattern: F) -> usize
@@ -108,34 +124,150 @@ where
     F: Fn(usize, usize, &'a [Vec<u8>]) -> usize,
 {
     let mut total = 0;
-    for y in 0..data.len() {
-        for x in 0..data[0].len() {
+    for (y, row) in data.iter().enumerate() {
+        for x in 0..row.len() {
             total += find_pattern(x, y, data);
         }
     }
     total
 }
 
-pub fn run() -> Result<()> {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Dir8 {
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+    North,
+    NorthEast,
+}
+
+impl Dir8 {
+    const ALL: [Dir8; 8] = [
+        Dir8::East,
+        Dir8::SouthEast,
+        Dir8::South,
+        Dir8::SouthWest,
+        Dir8::West,
+        Dir8::NorthWest,
+        Dir8::North,
+        Dir8::NorthEast,
+    ];
+
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Dir8::East => (1, 0),
+            Dir8::SouthEast => (1, 1),
+            Dir8::South => (0, 1),
+            Dir8::SouthWest => (-1, 1),
+            Dir8::West => (-1, 0),
+            Dir8::NorthWest => (-1, -1),
+            Dir8::North => (0, -1),
+            Dir8::NorthEast => (1, -1),
+        }
+    }
+}
+
+// start cell and direction of every occurrence of word
+fn find_word_matches<T: PartialEq>(grid: &[Vec<T>], word: &[T]) -> Vec<(usize, usize, Dir8)> {
+    if word.is_empty() {
+        return Vec::new();
+    }
+
+    let at = |x: isize, y: isize| {
+        let x = usize::try_from(x).ok()?;
+        let y = usize::try_from(y).ok()?;
+        grid.get(y)?.get(x)
+    };
+
+    let mut matches = Vec::new();
+    for (y, row) in grid.iter().enumerate() {
+        for x in 0..row.len() {
+            for dir in Dir8::ALL {
+                let (dx, dy) = dir.delta();
+                let found = word.iter().enumerate().all(|(i, c)| {
+                    let i = i as isize;
+                    at(x as isize + dx * i, y as isize + dy * i) == Some(c)
+                });
+                if found {
+                    matches.push((x, y, dir));
+                }
+            }
+        }
+    }
+    matches
+}
+
+#[allow(dead_code)]
+fn count_word(grid: &str, word: &str) -> usize {
+    let grid = grid
+        .split("\n")
+        .filter(|s| !s.is_empty())
+        .map(|s| s.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let word = word.chars().collect::<Vec<_>>();
+    find_word_matches(&grid, &word).len()
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 04");
     let path = PathBuf::from("./resources/day04.txt");
     let data = util::get_data_string(&path)?;
     let pattern = "XMAS";
-    let data = convert_to_vec_of_vecs(&data, pattern);
+    let data = convert_to_vec_of_vecs(&data, pattern)?;
     let pattern = pattern
         .chars()
         .map(|c| mapping_fn(c, pattern))
         .collect::<Vec<_>>();
-    let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
-    let count = find_patterns(&data, find_pattern);
-    println!("pattern count 1d: {count}");
-    let pattern = &pattern[1..];
-    let find_pattern = |x, y, v| find_pattern_2d(x, y, v, pattern);
-    let count = find_patterns(&data, find_pattern);
-    println!("pattern count 2d: {count}");
+    if part.one() {
+        let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
+        let count = find_patterns(&data, find_pattern);
+        println!("pattern count 1d: {count}");
+    }
+    if part.two() {
+        let pattern = &pattern[1..];
+        let find_pattern = |x, y, v| find_pattern_2d(x, y, v, pattern);
+        let count = find_patterns(&data, find_pattern);
+        println!("pattern count 2d: {count}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        4
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let pattern = "XMAS";
+        let data = convert_to_vec_of_vecs(&data, pattern)?;
+        let pattern = pattern
+            .chars()
+            .map(|c| mapping_fn(c, pattern))
+            .collect::<Vec<_>>();
+        let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
+        Ok(find_patterns(&data, find_pattern).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let pattern = "XMAS";
+        let data = convert_to_vec_of_vecs(&data, pattern)?;
+        let pattern = pattern
+            .chars()
+            .map(|c| mapping_fn(c, pattern))
+            .collect::<Vec<_>>();
+        let pattern = &pattern[1..];
+        let find_pattern = |x, y, v| find_pattern_2d(x, y, v, pattern);
+        Ok(find_patterns(&data, find_pattern).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,6 +281,47 @@ mod tests {
         assert_eq!(total, 1);
     }
 
+    #[test]
+    fn test_find_patterns_wildcard() {
+        let mapping = "X?AS";
+        let data = convert_to_vec_of_vecs("XMASXAASXMMS", mapping).unwrap();
+        let exact = [0, 4, 2, 3];
+        let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &exact);
+        assert_eq!(find_patterns(&data, find_pattern), 1);
+
+        let pattern = mapping
+            .chars()
+            .map(|c| mapping_fn(c, mapping))
+            .collect::<Vec<_>>();
+        assert_eq!(pattern, vec![0, WILDCARD, 2, 3]);
+        let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
+        assert_eq!(find_patterns(&data, find_pattern), 2);
+    }
+
+    #[test]
+    fn test_find_patterns_x_wildcard() {
+        let pattern: Vec<u8> = vec![1, WILDCARD, 3];
+        let data: Vec<Vec<u8>> = vec![vec![1, 4, 1], vec![4, 0, 4], vec![3, 4, 3]];
+        let find_pattern = |x, y, v| find_pattern_2d(x, y, v, &pattern);
+        assert_eq!(find_patterns(&data, find_pattern), 1);
+    }
+
+    #[test]
+    fn test_find_patterns_jagged() {
+        let mapping = "XMAS";
+        let input = "XMAS
+XM
+SAMXMAS
+X";
+        let data = convert_to_vec_of_vecs(input, mapping).unwrap();
+        let pattern = [0, 1, 2, 3];
+        let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
+        assert_eq!(find_patterns(&data, find_pattern), 3);
+        let find_pattern = |x, y, v| find_pattern_2d(x, y, v, &pattern[1..]);
+        assert_eq!(find_patterns(&data, find_pattern), 0);
+        assert!(convert_to_vec_of_vecs("\n", mapping).is_err());
+    }
+
     #[test]
     fn test_find_patterns_vertical() {
         let pattern: Vec<u8> = vec![0, 1, 2, 3];
@@ -180,6 +353,8 @@ mod tests {
         let find_pattern = |x, y, v| find_pattern_1d(x, y, v, &pattern);
         let total = find_patterns(&data, find_pattern);
         assert_eq!(total, 1);
+        let matches = find_word_matches(&data, &pattern);
+        assert_eq!(matches, vec![(3, 3, Dir8::NorthWest)]);
     }
 
     #[test]
@@ -213,4 +388,25 @@ mod tests {
         let total = find_patterns(&data, find_pattern);
         assert_eq!(total, 6);
     }
+
+    #[test]
+    fn test_count_word_short() {
+        let grid = "CAT.
+A...
+T.T.
+..A.";
+        assert_eq!(count_word(grid, "CAT"), 2);
+        assert_eq!(count_word(grid, "TAC"), 2);
+        assert_eq!(count_word(grid, "TAT"), 0);
+    }
+
+    #[test]
+    fn test_count_word_grid_width() {
+        let grid = "XMAS
+SAMX
+....";
+        assert_eq!(count_word(grid, "XMAS"), 2);
+        assert_eq!(count_word(grid, "XMASX"), 0);
+        assert_eq!(count_word(grid, "XMASXMASXMAS"), 0);
+    }
 }
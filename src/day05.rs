@@ -1,16 +1,13 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 struct Rule(usize, usize);
 
-impl Rule {
-    fn upheld(&self, left: usize, right: usize) -> bool {
-        !(right == self.0 && left == self.1)
-    }
-}
-
 fn parse_rules(input: &str) -> Result<Vec<Rule>> {
     input
         .split("\n")
@@ -44,80 +41,279 @@ fn separate_input(input: &str) -> Result<(Vec<Rule>, Vec<Vec<usize>>)> {
     Ok((rules, updates))
 }
 
-fn all_rules_upheld(left: usize, right: usize, rules: &[Rule]) -> bool {
-    rules.iter().map(|rule| rule.upheld(left, right)).all(|b| b)
+struct RuleSet(HashSet<(usize, usize)>);
+
+impl RuleSet {
+    fn new(rules: &[Rule]) -> Self {
+        RuleSet(
+            rules
+                .iter()
+                .map(|&Rule(left, right)| (left, right))
+                .collect(),
+        )
+    }
+
+    fn upheld(&self, left: usize, right: usize) -> bool {
+        !self.0.contains(&(right, left))
+    }
 }
 
-fn update_upholds_rules(update: &[usize], rules: &[Rule]) -> bool {
+fn update_upholds_rules(update: &[usize], rules: &RuleSet) -> bool {
     update
         .windows(2)
         .flat_map(<&[usize; 2]>::try_from)
-        .map(|&[left, right]| all_rules_upheld(left, right, rules))
-        .all(|b| b)
+        .all(|&[left, right]| rules.upheld(left, right))
 }
 
-fn quick_sort<T, F>(update: &mut [T], sort_criterion: &F)
-where
-    T: Copy,
-    F: Fn(T, T) -> bool,
-{
-    if update.len() < 2 {
-        return;
-    }
-
-    let mut first_after_divide = 1;
-    let length = update.len();
-    for index in 1..length {
-        if !sort_criterion(update[0], update[index]) {
-            if first_after_divide < index {
-                let (left, right) = update.split_at_mut(index);
-                let left = &mut left[first_after_divide];
-                let right = &mut right[0];
-                std::mem::swap(left, right);
+// the full rule set may well be cyclic, only the pages within each update need a consistent order
+fn validate_rules(rules: &[Rule], updates: &[Vec<usize>]) -> Result<()> {
+    #[derive(Copy, Clone, PartialEq, Eq)]
+    enum Mark {
+        Unseen,
+        Active,
+        Done,
+    }
+
+    fn find_back_edge(
+        page: usize,
+        edges: &HashMap<usize, Vec<usize>>,
+        marks: &mut HashMap<usize, Mark>,
+    ) -> Option<(usize, usize)> {
+        marks.insert(page, Mark::Active);
+        for &next in edges.get(&page).into_iter().flatten() {
+            match marks[&next] {
+                Mark::Active => return Some((page, next)),
+                Mark::Unseen => {
+                    if let Some(edge) = find_back_edge(next, edges, marks) {
+                        return Some(edge);
+                    }
+                }
+                Mark::Done => (),
             }
-            first_after_divide += 1;
         }
-    }
-    if first_after_divide > 1 {
-        let (left, right) = update.split_at_mut(first_after_divide - 1);
-        let left = &mut left[0];
-        let right = &mut right[0];
-        std::mem::swap(left, right);
+        marks.insert(page, Mark::Done);
+        None
     }
 
-    // skip the correctly placed checked value
-    let checked_pos = first_after_divide - 1;
-    if checked_pos > 0 {
-        quick_sort(&mut update[..checked_pos], sort_criterion);
+    for update in updates {
+        let pages = update.iter().copied().collect::<HashSet<_>>();
+        let mut edges: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &Rule(left, right) in rules {
+            if pages.contains(&left) && pages.contains(&right) {
+                edges.entry(left).or_default().push(right);
+            }
+        }
+
+        let mut marks = pages
+            .iter()
+            .map(|&p| (p, Mark::Unseen))
+            .collect::<HashMap<_, _>>();
+        for &page in update {
+            if marks[&page] != Mark::Unseen {
+                continue;
+            }
+            if let Some((a, b)) = find_back_edge(page, &edges, &mut marks) {
+                let msg = format!("contradictory rules between pages {a} and {b}");
+                return Err(AocError::ValueError(msg).into());
+            }
+        }
     }
-    if checked_pos < update.len() - 1 {
-        quick_sort(&mut update[checked_pos + 1..], sort_criterion);
+    Ok(())
+}
+
+// rules only order some pairs of pages, so this is a topological sort instead of a comparison
+// sort, pages without rules between them keep their relative order
+fn sort_update(update: &[usize], rules: &RuleSet) -> Result<Vec<usize>> {
+    let n_pages = update.len();
+    // look up every pair once, the placement loop below revisits them a lot
+    let precedes = update
+        .iter()
+        .flat_map(|&a| update.iter().map(move |&b| rules.0.contains(&(a, b))))
+        .collect::<Vec<_>>();
+    let must_precede = |a: usize, b: usize| precedes[a * n_pages + b];
+
+    // number of pages still to be placed that have to come before each page
+    let mut before = (0..n_pages)
+        .map(|i| (0..n_pages).filter(|&j| must_precede(j, i)).count())
+        .collect::<Vec<_>>();
+    let mut placed = vec![false; n_pages];
+    let mut sorted = Vec::with_capacity(n_pages);
+    while sorted.len() < n_pages {
+        let next = (0..n_pages)
+            .find(|&i| !placed[i] && before[i] == 0)
+            .ok_or_else(|| AocError::ValueError(format!("rules for {update:?} are cyclic")))?;
+        placed[next] = true;
+        sorted.push(update[next]);
+        for i in 0..n_pages {
+            if !placed[i] && must_precede(next, i) {
+                before[i] -= 1;
+            }
+        }
     }
+    Ok(sorted)
+}
+
+fn correct_updates(rules: &[Rule], updates: Vec<Vec<usize>>) -> Result<Vec<Vec<usize>>> {
+    let rules = RuleSet::new(rules);
+    updates
+        .into_iter()
+        .filter(|u| !update_upholds_rules(u, &rules))
+        .map(|u| sort_update(&u, &rules))
+        .collect()
+}
+
+fn mid_sum(updates: &[Vec<usize>]) -> usize {
+    updates.iter().map(|u| u[u.len() / 2]).sum()
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 05");
     let path = PathBuf::from("./resources/day05.txt");
     let data = util::get_data_string(&path)?;
-    let (rules, mut updates) = separate_input(&data)?;
+    let (rules, updates) = separate_input(&data)?;
+    validate_rules(&rules, &updates)?;
 
-    let mid_sum_correct = updates
-        .iter()
-        .filter(|u| update_upholds_rules(u, &rules))
-        .map(|u| u[u.len() / 2])
-        .sum::<usize>();
-    println!("mid sum of correct updates: {mid_sum_correct}");
-
-    let sort_criterion = |a, b| all_rules_upheld(a, b, &rules);
-    let mid_sum_incorrect = updates
-        .iter_mut()
-        .filter(|u| !update_upholds_rules(u, &rules))
-        .map(|u| {
-            quick_sort(u, &sort_criterion);
-            u[u.len() / 2]
-        })
-        .sum::<usize>();
-    println!("mid sum of incorrect updates: {mid_sum_incorrect}");
+    if part.one() {
+        let rule_set = RuleSet::new(&rules);
+        let mid_sum_correct = updates
+            .iter()
+            .filter(|u| update_upholds_rules(u, &rule_set))
+            .map(|u| u[u.len() / 2])
+            .sum::<usize>();
+        println!("mid sum of correct updates: {mid_sum_correct}");
+    }
+
+    if part.two() {
+        let corrected = correct_updates(&rules, updates)?;
+        let mid_sum_incorrect = mid_sum(&corrected);
+        println!("mid sum of incorrect updates: {mid_sum_incorrect}");
+    }
 
     Ok(())
 }
+
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        5
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (rules, updates) = separate_input(&data)?;
+        validate_rules(&rules, &updates)?;
+        let rule_set = RuleSet::new(&rules);
+        let mid_sum_correct = updates
+            .iter()
+            .filter(|u| update_upholds_rules(u, &rule_set))
+            .map(|u| u[u.len() / 2])
+            .sum::<usize>();
+        Ok(mid_sum_correct.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (rules, updates) = separate_input(&data)?;
+        validate_rules(&rules, &updates)?;
+        let corrected = correct_updates(&rules, updates)?;
+        Ok(mid_sum(&corrected).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+    #[test]
+    fn test_example_corrected() {
+        let (rules, updates) = separate_input(EXAMPLE).unwrap();
+        validate_rules(&rules, &updates).unwrap();
+        let corrected = correct_updates(&rules, updates).unwrap();
+        assert_eq!(corrected.len(), 3);
+        assert_eq!(corrected[0], vec![97, 75, 47, 61, 53]);
+        assert_eq!(corrected[1], vec![61, 29, 13]);
+        assert_eq!(corrected[2], vec![97, 75, 47, 29, 13]);
+        assert_eq!(mid_sum(&corrected), 123);
+    }
+
+    #[test]
+    fn test_large_rule_set() {
+        // every page has to come before all pages with a larger number
+        let pages = 300;
+        let rules = (0..pages)
+            .flat_map(|l| (l + 1..pages).map(move |r| Rule(l, r)))
+            .collect::<Vec<_>>();
+        assert_eq!(rules.len(), pages * (pages - 1) / 2);
+        let rules = RuleSet::new(&rules);
+
+        let sorted = (0..pages).collect::<Vec<_>>();
+        assert!(update_upholds_rules(&sorted, &rules));
+
+        let reversed = sorted.iter().rev().copied().collect::<Vec<_>>();
+        assert!(!update_upholds_rules(&reversed, &rules));
+        // sorting has to look at every pair of pages
+        let update = sort_update(&reversed, &rules).unwrap();
+        assert_eq!(update, sorted);
+    }
+
+    #[test]
+    fn test_incomplete_rules() {
+        // 2 and 4 are not ordered against anything
+        let rules = RuleSet::new(&[Rule(1, 3), Rule(5, 1)]);
+        let sorted = sort_update(&[3, 2, 1, 4, 5], &rules).unwrap();
+        assert_eq!(sorted, vec![2, 4, 5, 1, 3]);
+        assert!(update_upholds_rules(&sorted, &rules));
+
+        let rules = RuleSet::new(&[Rule(1, 2), Rule(2, 1)]);
+        assert!(sort_update(&[2, 1], &rules).is_err());
+    }
+
+    #[test]
+    fn test_contradictory_rules() {
+        let rules = vec![Rule(1, 2), Rule(2, 1), Rule(3, 4)];
+        let updates = vec![vec![3, 4], vec![4, 2, 1]];
+        let err = validate_rules(&rules, &updates).unwrap_err();
+        assert!(err.to_string().contains("pages 1 and 2"));
+
+        let updates = vec![vec![3, 4], vec![1, 3]];
+        assert!(validate_rules(&rules, &updates).is_ok());
+    }
+
+    #[test]
+    fn test_longer_cycle() {
+        let rules = vec![Rule(1, 2), Rule(2, 3), Rule(3, 1)];
+        assert!(validate_rules(&rules, &[vec![1, 2, 3]]).is_err());
+        assert!(validate_rules(&rules, &[vec![1, 2]]).is_ok());
+    }
+}
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use std::{fmt::Write, ops::BitOr, path::PathBuf};
+use std::{
+    fmt::Write,
+    ops::BitOr,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Direction {
@@ -152,7 +156,6 @@ struct Map {
     tiles: Vec<Vec<Tile>>,
     guard_pos: Option<(usize, usize)>,
     visited: usize,
-    loops: usize,
 }
 
 impl std::fmt::Debug for Map {
@@ -205,15 +208,28 @@ impl Map {
             .collect::<Result<_>>()?;
 
         let visited = 1;
-        let loops = 0;
         Ok(Map {
             tiles,
             guard_pos: guard,
             visited,
-            loops,
         })
     }
 
+    fn visited_positions(&self) -> Vec<(usize, usize)> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter_map(move |(x, tile)| match tile {
+                        Tile::Visited(_) | Tile::Guard(..) => Some((x, y)),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
     fn next_pos(&self) -> Option<(usize, usize)> {
         let (x, y) = self.guard_pos?;
         let Tile::Guard(guard, _) = self.tiles[y][x] else {
@@ -228,46 +244,6 @@ impl Map {
         }
     }
 
-    fn is_in_loop(&self) -> bool {
-        let Some((next_x, next_y)) = self.next_pos() else {
-            return false;
-        };
-        let Tile::Visited(next_visited) = self.tiles[next_y][next_x] else {
-            return false;
-        };
-        let (cur_x, cur_y) = self.guard_pos.unwrap();
-        let Tile::Guard(Guard(cur_dir), _) = self.tiles[cur_y][cur_x] else {
-            unreachable!()
-        };
-        next_visited.matches_direction(&cur_dir)
-    }
-
-    fn would_loop_here(&self) -> bool {
-        let mut loop_map = self.clone();
-        let Some((next_x, next_y)) = loop_map.next_pos() else {
-            return false;
-        };
-        match loop_map.tiles[next_y][next_x] {
-            Tile::Obstacle => return false,
-            Tile::Visited(_) => return false,
-            _ => (),
-        }
-        loop_map.tiles[next_y][next_x] = Tile::Obstacle;
-
-        loop {
-            match loop_map.step() {
-                State::Complete => {
-                    break false;
-                }
-                State::Ongoing => {
-                    if loop_map.is_in_loop() {
-                        break true;
-                    }
-                }
-            }
-        }
-    }
-
     fn step(&mut self) -> State {
         let Some((cur_x, cur_y)) = self.guard_pos else {
             return State::Complete;
@@ -311,77 +287,102 @@ impl Map {
         }
     }
 
-    fn step_and_count_loops(&mut self) -> State {
-        let next_pos = self.next_pos();
+    #[allow(dead_code)]
+    fn render_path(&self) -> String {
+        let mut walked = self.clone();
+        while let State::Ongoing = walked.step() {}
+        format!("{walked:?}")
+    }
 
-        let Some((cur_x, cur_y)) = self.guard_pos else {
-            return State::Complete;
+    // simulate from the start without touching the map, tracking states in a separate grid
+    fn loops_with_obstacle(&self, obstacle: (usize, usize)) -> bool {
+        let Some((mut x, mut y)) = self.guard_pos else {
+            return false;
         };
-        let Tile::Guard(guard, cur_visited) = self.tiles[cur_y][cur_x] else {
+        let Tile::Guard(mut guard, _) = self.tiles[y][x] else {
             unreachable!();
         };
+        let height = self.tiles.len();
+        let width = self.tiles[0].len();
+        let mut seen = vec![Visited::default(); width * height];
 
-        let Some((next_x, next_y)) = next_pos else {
-            let new_visited = Visited::new(&guard.0);
-            self.tiles[cur_y][cur_x] = Tile::Visited(cur_visited | new_visited);
-            self.guard_pos = None;
-            return State::Complete;
-        };
-
-        let next_tile = self.tiles[next_y][next_x];
-
-        if self.would_loop_here() {
-            self.loops += 1;
-        }
+        loop {
+            let cur_seen = &mut seen[y * width + x];
+            if cur_seen.matches_direction(&guard.0) {
+                return true;
+            }
+            *cur_seen = *cur_seen | Visited::new(&guard.0);
 
-        match next_tile {
-            Tile::Empty => {
-                let new_visited = Visited::new(&guard.0);
-                self.visited += 1;
-                self.tiles[cur_y][cur_x] = Tile::Visited(cur_visited);
-                self.tiles[next_y][next_x] = Tile::Guard(guard, new_visited);
-                self.guard_pos = next_pos;
-                State::Ongoing
+            let Some((next_x, next_y)) = guard.0.looking_at(x, y) else {
+                return false;
+            };
+            if next_x >= width || next_y >= height {
+                return false;
             }
-            Tile::Visited(next_visited) => {
-                let new_visited = Visited::new(&guard.0);
-                self.tiles[cur_y][cur_x] = Tile::Visited(cur_visited);
-                self.tiles[next_y][next_x] = Tile::Guard(guard, next_visited | new_visited);
-                self.guard_pos = next_pos;
-                State::Ongoing
+            if (next_x, next_y) == obstacle || matches!(self.tiles[next_y][next_x], Tile::Obstacle)
+            {
+                guard = guard.turn();
+            } else {
+                (x, y) = (next_x, next_y);
             }
-            Tile::Obstacle => {
-                let guard = guard.turn();
-                let new_visited = Visited::new(&guard.0);
-                self.tiles[cur_y][cur_x] = Tile::Guard(guard, cur_visited | new_visited);
-                State::Ongoing
-            }
-            Tile::Guard(..) => unreachable!(),
         }
     }
 }
 
-pub fn run() -> Result<()> {
+// only cells on the original path can change the guard's route
+fn count_loop_obstacles(map: &Map) -> usize {
+    let start = map.guard_pos;
+    let mut walked = map.clone();
+    while let State::Ongoing = walked.step() {}
+    walked
+        .visited_positions()
+        .into_iter()
+        .filter(|&pos| Some(pos) != start)
+        .filter(|&pos| map.loops_with_obstacle(pos))
+        .count()
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 06");
     let path = PathBuf::from("./resources/day06.txt");
     let data = util::get_data_string(&path)?;
-    let mut map = Map::new(&data)?;
-    let mut loop_map = map.clone();
-
-    while let State::Ongoing = map.step() {}
-    let visited = map.visited;
-    println!("visited: {visited}");
-
-    let first_step_loops = map.would_loop_here();
-    while let State::Ongoing = loop_map.step_and_count_loops() {}
-    let loops = match first_step_loops {
-        true => loop_map.loops - 1,
-        false => loop_map.loops,
-    };
-    println!("loops: {loops}");
+    let map = Map::new(&data)?;
+
+    if part.one() {
+        let mut map = map.clone();
+        while let State::Ongoing = map.step() {}
+        let visited = map.visited;
+        println!("visited: {visited}");
+    }
+
+    if part.two() {
+        let loops = count_loop_obstacles(&map);
+        println!("loops: {loops}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        6
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let mut map = Map::new(&data)?;
+        while let State::Ongoing = map.step() {}
+        Ok(map.visited.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let map = Map::new(&data)?;
+        Ok(count_loop_obstacles(&map).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -400,7 +401,7 @@ mod tests {
 ......#...";
         let mut map = Map::new(input).unwrap();
         loop {
-            println!("{map:?}");
+            util::log!("{map:?}");
             match map.step() {
                 State::Ongoing => (),
                 State::Complete => break,
@@ -408,6 +409,29 @@ mod tests {
         }
         let visited = map.visited;
         assert_eq!(visited, 41);
+        let positions = map.visited_positions();
+        assert_eq!(positions.len(), 41);
+        assert!(positions.contains(&(4, 6)));
+        assert!(positions.contains(&(7, 9)));
+    }
+
+    #[test]
+    fn test_render_path() {
+        let input = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+        let map = Map::new(input).unwrap();
+        let rendered = map.render_path();
+        assert_eq!(rendered.matches('+').count(), 14);
+        assert!(!rendered.contains('^'));
+        assert!(rendered.starts_with("....#.....\n....+---+#\n"));
     }
 
     #[test]
@@ -422,15 +446,9 @@ mod tests {
 ........#.
 #.........
 ......#...";
-        let mut map = Map::new(input).unwrap();
-        loop {
-            println!("{map:?}");
-            match map.step_and_count_loops() {
-                State::Ongoing => (),
-                State::Complete => break,
-            }
-        }
-        let loops = map.loops;
+        let map = Map::new(input).unwrap();
+        util::log!("{map:?}");
+        let loops = count_loop_obstacles(&map);
         assert_eq!(loops, 6);
     }
 
@@ -446,15 +464,72 @@ mod tests {
 .#....#...
 ...#.#....
 ..........";
-        let mut map = Map::new(input).unwrap();
-        loop {
-            println!("{map:?}");
-            match map.step_and_count_loops() {
-                State::Ongoing => (),
-                State::Complete => break,
-            }
-        }
-        let loops = map.loops;
+        let map = Map::new(input).unwrap();
+        util::log!("{map:?}");
+        let loops = count_loop_obstacles(&map);
         assert_eq!(loops, 4);
     }
+
+    #[test]
+    fn test_loop_start_facing_right() {
+        let input = "..........
+....#.....
+..#....#..
+.#........
+......#...
+..........
+..#.>.....
+.#....#...
+...#.#....
+..........";
+        let map = Map::new(input).unwrap();
+        assert_eq!(count_loop_obstacles(&map), 1);
+
+        let input = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..>.....
+........#.
+#.........
+......#...";
+        let mut map = Map::new(input).unwrap();
+        assert_eq!(count_loop_obstacles(&map), 0);
+        while let State::Ongoing = map.step() {}
+        assert_eq!(map.visited, 6);
+    }
+
+    #[test]
+    fn test_loop_start_facing_down() {
+        let input = "..........
+....#.....
+..#....#..
+.#........
+......#...
+..........
+..#.v.....
+.#....#...
+...#.#....
+..........";
+        let map = Map::new(input).unwrap();
+        assert_eq!(count_loop_obstacles(&map), 1);
+    }
+
+    #[test]
+    fn test_loop_start_facing_left() {
+        let input = "..........
+....#.....
+..#....#..
+.#........
+......#...
+..........
+..#.<.....
+.#....#...
+...#.#....
+..........";
+        let map = Map::new(input).unwrap();
+        assert_eq!(count_loop_obstacles(&map), 2);
+    }
 }
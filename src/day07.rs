@@ -1,58 +1,115 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
+#[derive(Debug, PartialEq, Eq)]
 struct Equation {
     target: usize,
     values: Vec<usize>,
 }
 
+fn parse_equation(s: &str) -> Result<Equation> {
+    let split_index = s.find(':').ok_or(AocError::ParseError)?;
+    let (value, rest) = s.split_at(split_index);
+    let value: usize = value.parse()?;
+    let operands = &rest[1..].trim();
+    let operands = operands
+        .split_whitespace()
+        .map(|o| Ok(o.parse()?))
+        .collect::<Result<Vec<_>>>()?;
+    if operands.is_empty() {
+        return Err(AocError::ParseError.into());
+    }
+    Ok(Equation {
+        target: value,
+        values: operands,
+    })
+}
+
 fn generate_equations(input: &str) -> Result<Vec<Equation>> {
     input
         .split("\n")
         .filter(|s| !s.is_empty())
-        .map(|s| {
-            let split_index = s.find(':').ok_or(AocError::ParseError)?;
-            let (value, rest) = s.split_at(split_index);
-            let value: usize = value.parse()?;
-            let operands = &rest[1..].trim();
-            let operands = operands
-                .split_whitespace()
-                .map(|o| Ok(o.parse()?))
-                .collect::<Result<Vec<_>>>()?;
-            if operands.is_empty() {
-                return Err(AocError::ParseError.into());
-            }
-            Ok(Equation {
-                target: value,
-                values: operands,
-            })
-        })
+        .map(parse_equation)
         .collect::<Result<_>>()
 }
 
-enum NextOperators {
-    Mul,
+// streaming parses line by line without holding the whole file in memory
+fn load_equations(path: &Path, streaming: bool) -> Result<Vec<Equation>> {
+    if streaming {
+        generate_equations_streaming(path)
+    } else {
+        generate_equations(&util::get_data_string(path)?)
+    }
+}
+
+fn generate_equations_streaming(path: &Path) -> Result<Vec<Equation>> {
+    let mut equations = Vec::new();
+    util::for_each_line(path, |s| {
+        if !s.is_empty() {
+            equations.push(parse_equation(s)?);
+        }
+        Ok(())
+    })?;
+    Ok(equations)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
     Add,
+    Mul,
     Concat,
-    Done,
+    Sub,
+}
+
+impl Op {
+    // None when the result is not representable, which prunes the branch
+    fn apply(self, left: usize, right: usize) -> Option<usize> {
+        match self {
+            Op::Add => left.checked_add(right),
+            Op::Mul => left.checked_mul(right),
+            Op::Concat => concat(left, right),
+            Op::Sub => left.checked_sub(right),
+        }
+    }
 }
 
 struct State {
     index: usize,
     result: usize,
-    next_op: NextOperators,
+    next_op: usize,
+    last_op: Option<Op>,
 }
 
-// Depth First Search with backtracking when result gets too large
-fn can_construct_equation(equation: &Equation) -> bool {
+fn digits(mut value: usize) -> u32 {
+    let mut count = 1;
+    while value >= 10 {
+        value /= 10;
+        count += 1;
+    }
+    count
+}
+
+fn concat(left: usize, right: usize) -> Option<usize> {
+    let shift = 10usize.checked_pow(digits(right))?;
+    left.checked_mul(shift)?.checked_add(right)
+}
+
+// Depth First Search with backtracking when result gets too large,
+// returns the operators of the first solution from left to right
+fn solve_equation(equation: &Equation, ops: &[Op]) -> Option<Vec<Op>> {
     let Equation { target, values } = equation;
+    let &first = values.first()?;
+    // with subtraction a result that is too large could still come back down
+    let can_prune = !ops.contains(&Op::Sub);
+    let mut path = Vec::new();
     let mut stack = Vec::new();
     let initial_state = State {
         index: 0,
-        result: equation.values[0],
-        next_op: NextOperators::Mul,
+        result: first,
+        next_op: 0,
+        last_op: None,
     };
     stack.push(initial_state);
     while let Some(top) = stack.pop() {
@@ -60,115 +117,57 @@ fn can_construct_equation(equation: &Equation) -> bool {
             index,
             result,
             next_op,
+            last_op,
         } = top;
 
+        // the path holds the operators that lead to this state
+        path.truncate(index.saturating_sub(1));
+        path.extend(last_op);
+
         let next_index = index + 1;
         if next_index == values.len() {
             if result == *target {
-                return true;
+                return Some(path);
             } else {
                 continue;
             }
         }
-        if result > *target {
+        if can_prune && result > *target {
             continue;
         }
-        let next_value = values[next_index];
-        let next_result = match next_op {
-            NextOperators::Mul => {
-                stack.push(State {
-                    index,
-                    result,
-                    next_op: NextOperators::Add,
-                });
-                result * next_value
-            }
-            NextOperators::Add => {
-                stack.push(State {
-                    index,
-                    result,
-                    next_op: NextOperators::Done,
-                });
-                result + next_value
-            }
-            NextOperators::Done => continue,
-            NextOperators::Concat => unreachable!(),
+        let Some(&op) = ops.get(next_op) else {
+            continue;
         };
-
         stack.push(State {
-            index: next_index,
-            result: next_result,
-            next_op: NextOperators::Mul,
-        });
-    }
-    false
-}
-
-// Depth First Search with backtracking when result gets too large
-fn can_construct_equation_with_concat(equation: &Equation) -> bool {
-    let Equation { target, values } = equation;
-    let mut stack = Vec::new();
-    let initial_state = State {
-        index: 0,
-        result: equation.values[0],
-        next_op: NextOperators::Mul,
-    };
-    stack.push(initial_state);
-    while let Some(top) = stack.pop() {
-        let State {
             index,
             result,
-            next_op,
-        } = top;
+            next_op: next_op + 1,
+            last_op,
+        });
 
-        let next_index = index + 1;
-        if next_index == values.len() {
-            if result == *target {
-                return true;
-            } else {
-                continue;
-            }
-        }
-        if result > *target {
+        let Some(next_result) = op.apply(result, values[next_index]) else {
             continue;
-        }
-        let next_value = values[next_index];
-        let next_result = match next_op {
-            NextOperators::Mul => {
-                stack.push(State {
-                    index,
-                    result,
-                    next_op: NextOperators::Add,
-                });
-                result * next_value
-            }
-            NextOperators::Add => {
-                stack.push(State {
-                    index,
-                    result,
-                    next_op: NextOperators::Concat,
-                });
-                result + next_value
-            }
-            NextOperators::Concat => {
-                stack.push(State {
-                    index,
-                    result,
-                    next_op: NextOperators::Done,
-                });
-                let mut concatted = result.to_string();
-                concatted.push_str(&next_value.to_string());
-                concatted.parse().unwrap()
-            }
-            NextOperators::Done => continue,
         };
         stack.push(State {
             index: next_index,
             result: next_result,
-            next_op: NextOperators::Mul,
+            next_op: 0,
+            last_op: Some(op),
         });
     }
-    false
+    None
+}
+
+fn can_construct(equation: &Equation, ops: &[Op]) -> bool {
+    solve_equation(equation, ops).is_some()
+}
+
+fn can_construct_equation(equation: &Equation) -> bool {
+    can_construct(equation, &[Op::Add, Op::Mul])
+}
+
+fn can_construct_equation_with_concat(equation: &Equation) -> bool {
+    can_construct(equation, &[Op::Add, Op::Mul, Op::Concat])
 }
 
 fn get_total_calibration_result(equations: &[Equation]) -> usize {
@@ -187,14 +186,142 @@ fn get_total_calibration_result_with_concat(equations: &[Equation]) -> usize {
         .sum()
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 07");
     let path = PathBuf::from("./resources/day07.txt");
-    let data = util::get_data_string(&path)?;
-    let equations = generate_equations(&data)?;
-    let result = get_total_calibration_result(&equations);
-    println!("total calibration result: {result}");
-    let result = get_total_calibration_result_with_concat(&equations);
-    println!("total calibration result with concat: {result}");
+    let equations = load_equations(&path, true)?;
+    if part.one() {
+        let result = get_total_calibration_result(&equations);
+        println!("total calibration result: {result}");
+    }
+    if part.two() {
+        let result = get_total_calibration_result_with_concat(&equations);
+        println!("total calibration result with concat: {result}");
+    }
     Ok(())
 }
+
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        7
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let equations = load_equations(path, true)?;
+        Ok(get_total_calibration_result(&equations).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let equations = load_equations(path, true)?;
+        Ok(get_total_calibration_result_with_concat(&equations).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20
+";
+
+    #[test]
+    fn test_example_calibration() {
+        let equations = generate_equations(EXAMPLE).unwrap();
+        let constructible = equations
+            .iter()
+            .filter(|e| can_construct_equation(e))
+            .map(|e| e.target)
+            .collect::<Vec<_>>();
+        assert_eq!(constructible, vec![190, 3267, 292]);
+        assert_eq!(get_total_calibration_result(&equations), 3749);
+    }
+
+    #[test]
+    fn test_example_calibration_with_concat() {
+        let equations = generate_equations(EXAMPLE).unwrap();
+        let constructible = equations
+            .iter()
+            .filter(|e| can_construct_equation_with_concat(e))
+            .map(|e| e.target)
+            .collect::<Vec<_>>();
+        assert_eq!(constructible, vec![190, 3267, 156, 7290, 192, 292]);
+        assert_eq!(get_total_calibration_result_with_concat(&equations), 11387);
+    }
+
+    #[test]
+    fn test_concat() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(10), 2);
+        assert_eq!(digits(usize::MAX), usize::MAX.to_string().len() as u32);
+        assert_eq!(concat(12, 345), Some(12345));
+        assert_eq!(concat(15, 0), Some(150));
+        assert_eq!(concat(usize::MAX / 10, 99), None);
+        assert_eq!(concat(1, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_subtraction() {
+        let equations = generate_equations("5: 10 5\n3: 5 4 2").unwrap();
+        assert!(!can_construct_equation_with_concat(&equations[0]));
+        assert!(can_construct(&equations[0], &[Op::Sub]));
+        assert!(!can_construct(&equations[1], &[Op::Add, Op::Mul]));
+        assert!(can_construct(&equations[1], &[Op::Add, Op::Mul, Op::Sub]));
+    }
+
+    #[test]
+    fn test_solve_equation() {
+        let equations = generate_equations("3267: 81 40 27\n5: 5").unwrap();
+        let equation = &equations[0];
+        let ops = solve_equation(equation, &[Op::Add, Op::Mul]).unwrap();
+        assert_eq!(ops.len(), 2);
+        let result = ops
+            .iter()
+            .zip(&equation.values[1..])
+            .try_fold(equation.values[0], |acc, (op, &v)| op.apply(acc, v));
+        assert_eq!(result, Some(3267));
+        assert_eq!(solve_equation(&equations[1], &[Op::Add]), Some(vec![]));
+        let empty = Equation {
+            target: 0,
+            values: vec![],
+        };
+        assert_eq!(solve_equation(&empty, &[Op::Add]), None);
+    }
+
+    #[test]
+    fn test_single_value() {
+        let equations = generate_equations("5: 5\n6: 5").unwrap();
+        assert!(can_construct_equation(&equations[0]));
+        assert!(can_construct_equation_with_concat(&equations[0]));
+        assert!(!can_construct_equation(&equations[1]));
+        assert!(!can_construct_equation_with_concat(&equations[1]));
+    }
+
+    #[test]
+    fn test_streaming_same_as_in_memory() {
+        let input = EXAMPLE;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let name = format!("aoc2024_day07_{}_{nanos}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, input).unwrap();
+        let streamed = load_equations(&path, true);
+        let in_memory = load_equations(&path, false);
+        std::fs::remove_file(&path).unwrap();
+        let (streamed, in_memory) = (streamed.unwrap(), in_memory.unwrap());
+        assert_eq!(streamed.len(), 9);
+        assert_eq!(streamed, in_memory);
+    }
+}
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
-use crate::util;
+use crate::util::{self, Part, Solution};
 
 enum Tile {
     Empty(bool),
@@ -56,6 +59,12 @@ impl Map {
     }
 }
 
+fn in_bounds(tiles: &[Vec<Tile>], x: usize, y: usize) -> bool {
+    y < tiles.len() && x < tiles[y].len()
+}
+
+// the antinode mirrors the far antenna at the near one, i.e. 2 * near - far,
+// which is out of bounds whenever the subtraction would underflow
 fn antinode_at(tiles: &[Vec<Tile>], near: Antenna, far: Antenna) -> Option<(usize, usize)> {
     let Antenna {
         pos: (x_near, y_near),
@@ -63,22 +72,10 @@ fn antinode_at(tiles: &[Vec<Tile>], near: Antenna, far: Antenna) -> Option<(usiz
     let Antenna {
         pos: (x_far, y_far),
     } = far;
-    let x_near = x_near as isize;
-    let y_near = y_near as isize;
-    let x_far = x_far as isize;
-    let y_far = y_far as isize;
-
-    let anti_x = x_near + x_near - x_far;
-    let anti_y = y_near + y_near - y_far;
 
-    let width = tiles[0].len() as isize;
-    let height = tiles.len() as isize;
-
-    if 0 <= anti_x && anti_x < width && 0 <= anti_y && anti_y < height {
-        Some((anti_x as usize, anti_y as usize))
-    } else {
-        None
-    }
+    let anti_x = (2 * x_near).checked_sub(x_far)?;
+    let anti_y = (2 * y_near).checked_sub(y_far)?;
+    in_bounds(tiles, anti_x, anti_y).then_some((anti_x, anti_y))
 }
 
 fn set_antinodes(map: &mut Map) {
@@ -100,6 +97,28 @@ fn set_antinodes(map: &mut Map) {
     }
 }
 
+// unlike set_antinodes this does not touch the tiles, a position shared by
+// two frequencies is listed for both but only once per frequency
+#[allow(dead_code)]
+fn antinodes_by_frequency(map: &Map) -> HashMap<char, Vec<(usize, usize)>> {
+    let mut by_frequency = HashMap::new();
+    for (&c, Frequency { antennas }) in map.frequencies.iter() {
+        let mut positions = HashSet::new();
+        for (p, &near) in antennas.iter().enumerate() {
+            for (q, &far) in antennas.iter().enumerate() {
+                if p == q {
+                    continue;
+                }
+                positions.extend(antinode_at(&map.tiles, near, far));
+            }
+        }
+        let mut positions = positions.into_iter().collect::<Vec<_>>();
+        positions.sort_by_key(|&(x, y)| (y, x));
+        by_frequency.insert(c, positions);
+    }
+    by_frequency
+}
+
 fn set_harmonics_line(tiles: &mut [Vec<Tile>], mut near: Antenna, mut far: Antenna) {
     while let Some((x, y)) = antinode_at(tiles, near, far) {
         tiles[y][x].activate();
@@ -129,28 +148,120 @@ fn set_resonant_harmonics(map: &mut Map) {
     }
 }
 
+fn antinode_positions(map: &Map) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+    for (y, row) in map.tiles.iter().enumerate() {
+        for (x, t) in row.iter().enumerate() {
+            let active = match t {
+                Tile::Empty(active) => active,
+                Tile::Antenna(_, active) => active,
+            };
+            if *active {
+                positions.push((x, y));
+            }
+        }
+    }
+    positions
+}
+
 fn count_antinodes(map: &Map) -> usize {
-    map.tiles
-        .iter()
-        .flatten()
-        .map(|t| match t {
-            Tile::Empty(active) => active,
-            Tile::Antenna(_, active) => active,
-        })
-        .filter(|&&b| b)
-        .count()
+    antinode_positions(map).len()
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 08");
     let path = PathBuf::from("./resources/day08.txt");
     let data = util::get_data_string(&path)?;
     let mut map = Map::parse(&data)?;
-    set_antinodes(&mut map);
-    let antinodes = count_antinodes(&map);
-    println!("antinodes: {antinodes}");
-    set_resonant_harmonics(&mut map);
-    let antinodes = count_antinodes(&map);
-    println!("antinodes with resonant harmonics: {antinodes}");
+    if part.one() {
+        set_antinodes(&mut map);
+        let antinodes = count_antinodes(&map);
+        println!("antinodes: {antinodes}");
+    }
+    if part.two() {
+        set_resonant_harmonics(&mut map);
+        let antinodes = count_antinodes(&map);
+        println!("antinodes with resonant harmonics: {antinodes}");
+    }
     Ok(())
 }
+
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        8
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let mut map = Map::parse(&data)?;
+        set_antinodes(&mut map);
+        Ok(count_antinodes(&map).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let mut map = Map::parse(&data)?;
+        set_resonant_harmonics(&mut map);
+        Ok(count_antinodes(&map).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "............
+........0...
+.....0......
+.......0....
+....0.......
+......A.....
+............
+............
+........A...
+.........A..
+............
+............
+";
+
+    #[test]
+    fn test_example_antinodes() {
+        let mut map = Map::parse(EXAMPLE).unwrap();
+        set_antinodes(&mut map);
+        let positions = antinode_positions(&map);
+        assert_eq!(positions.len(), 14);
+        assert!(positions.contains(&(6, 0)));
+        assert!(positions.contains(&(6, 5)));
+        assert_eq!(count_antinodes(&map), 14);
+    }
+
+    #[test]
+    fn test_example_resonant_harmonics() {
+        let mut map = Map::parse(EXAMPLE).unwrap();
+        set_resonant_harmonics(&mut map);
+        assert_eq!(antinode_positions(&map).len(), 34);
+    }
+
+    #[test]
+    fn test_antinodes_by_frequency() {
+        let map = Map::parse(EXAMPLE).unwrap();
+        let by_frequency = antinodes_by_frequency(&map);
+        assert_eq!(by_frequency.len(), 2);
+        let zero = &by_frequency[&'0'];
+        let a = &by_frequency[&'A'];
+        assert_ne!(zero, a);
+        assert!(a.contains(&(10, 11)));
+        assert!(!zero.contains(&(10, 11)));
+        let all = zero.iter().chain(a.iter()).collect::<HashSet<_>>();
+        assert_eq!(all.len(), 14);
+    }
+
+    #[test]
+    fn test_single_antenna() {
+        let mut map = Map::parse("....\n.a..\n..b.\n....").unwrap();
+        set_antinodes(&mut map);
+        assert!(antinode_positions(&map).is_empty());
+    }
+}
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 struct FileBlock {
@@ -16,26 +20,26 @@ struct Layout {
     size: usize,
 }
 
-impl std::fmt::Debug for Layout {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Layout {
+    fn render(&self) -> String {
+        let mut out = String::new();
         let mut last_end = 0;
         for file in self.blocks.iter() {
             let diff = file.start - last_end;
-            if diff > 0 {
-                let free = ".".to_string().repeat(diff);
-                f.write_str(&free)?;
-            }
+            out.push_str(&".".repeat(diff));
             let row = file.id.to_string().repeat(file.count);
-            f.write_str(&row)?;
+            out.push_str(&row);
             last_end = file.start + file.count;
         }
         let diff = self.size - last_end;
-        if diff > 0 {
-            let free = ".".to_string().repeat(diff);
-            f.write_str(&free)?;
-        }
+        out.push_str(&".".repeat(diff));
+        out
+    }
+}
 
-        Ok(())
+impl std::fmt::Debug for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render())
     }
 }
 
@@ -124,39 +128,74 @@ fn defragment_blockwise(layout: &Layout) -> Layout {
     }
 }
 
-fn compute_checksum(layout: &Layout) -> usize {
+// accumulate in u128 so large disks cannot overflow, even on 32-bit targets
+fn compute_checksum(layout: &Layout) -> u128 {
     let mut checksum = 0;
     for file in layout.blocks.iter() {
         for step in 0..file.count {
-            checksum += file.id * (file.start + step);
+            checksum += file.id as u128 * (file.start + step) as u128;
         }
     }
     checksum
 }
 
+// free space runs bucketed by their length, each bucket yields its leftmost run first
+struct FreeLists {
+    buckets: Vec<BinaryHeap<Reverse<usize>>>,
+}
+
+impl FreeLists {
+    fn new(layout: &Layout) -> FreeLists {
+        let mut gaps = Vec::new();
+        let mut last_end = 0;
+        for file in layout.blocks.iter() {
+            gaps.push((last_end, file.start - last_end));
+            last_end = file.start + file.count;
+        }
+        gaps.push((last_end, layout.size - last_end));
+
+        let max_len = gaps.iter().map(|&(_, len)| len).max().unwrap_or(0);
+        let mut buckets = vec![BinaryHeap::new(); max_len + 1];
+        for (start, len) in gaps {
+            if len > 0 {
+                buckets[len].push(Reverse(start));
+            }
+        }
+        FreeLists { buckets }
+    }
+
+    // take the leftmost run before `limit` that can hold `count` blocks,
+    // the part of the run that is not needed goes back into its bucket
+    fn take(&mut self, count: usize, limit: usize) -> Option<usize> {
+        let (len, start) = self
+            .buckets
+            .iter()
+            .enumerate()
+            .skip(count)
+            .filter_map(|(len, bucket)| bucket.peek().map(|&Reverse(start)| (len, start)))
+            .filter(|&(_, start)| start < limit)
+            .min_by_key(|&(_, start)| start)?;
+        self.buckets[len].pop();
+        if len > count {
+            self.buckets[len - count].push(Reverse(start + count));
+        }
+        Some(start)
+    }
+}
+
 fn defrag_filewise(layout: &Layout) -> Layout {
+    let mut free = FreeLists::new(layout);
     let mut new_blocks = layout.blocks.clone();
 
-    for file in layout.blocks.iter().rev() {
-        let mut last_index = 0;
-        for (index, run_file) in new_blocks.iter().enumerate() {
-            let space = run_file.start - last_index;
-            if space >= file.count {
-                let new_file = FileBlock {
-                    id: file.id,
-                    start: last_index,
-                    count: file.count,
-                };
-                new_blocks.insert(index, new_file);
-                let remove_index = new_blocks.iter().rposition(|f| f.id == file.id).unwrap();
-                new_blocks.remove(remove_index);
-                break;
-            }
-            last_index = run_file.start + run_file.count;
+    // space vacated by a file lies right of every file still to be moved,
+    // so it never needs to go back into the free lists
+    for file in new_blocks.iter_mut().rev() {
+        if let Some(start) = free.take(file.count, file.start) {
+            file.start = start;
         }
     }
 
-    new_blocks.sort_by(|l, r| l.start.cmp(&r.start));
+    new_blocks.sort_by_key(|f| f.start);
 
     Layout {
         blocks: new_blocks,
@@ -164,20 +203,44 @@ fn defrag_filewise(layout: &Layout) -> Layout {
     }
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 09");
     let path = PathBuf::from("./resources/day09.txt");
     let data = util::get_data_string(&path)?;
     let layout = get_layout(&data)?;
-    let defrag_block = defragment_blockwise(&layout);
-    let checksum_block = compute_checksum(&defrag_block);
-    println!("checksum blockwise: {checksum_block}");
-    let defrag_file = defrag_filewise(&layout);
-    let checksum_file = compute_checksum(&defrag_file);
-    println!("checksum filewise: {checksum_file}");
+    if part.one() {
+        let defrag_block = defragment_blockwise(&layout);
+        let checksum_block = compute_checksum(&defrag_block);
+        println!("checksum blockwise: {checksum_block}");
+    }
+    if part.two() {
+        let defrag_file = defrag_filewise(&layout);
+        let checksum_file = compute_checksum(&defrag_file);
+        println!("checksum filewise: {checksum_file}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        9
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let layout = get_layout(&data)?;
+        Ok(compute_checksum(&defragment_blockwise(&layout)).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let layout = get_layout(&data)?;
+        Ok(compute_checksum(&defrag_filewise(&layout)).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,4 +262,57 @@ mod tests {
         let checksum = compute_checksum(&defrag);
         assert_eq!(checksum, 2858);
     }
+
+    #[test]
+    fn test_render() {
+        let input = "2333133121414131402";
+        let layout = get_layout(input).unwrap();
+        assert_eq!(
+            layout.render(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+        let defrag = defragment_blockwise(&layout);
+        assert_eq!(
+            defrag.render(),
+            "0099811188827773336446555566.............."
+        );
+        let defrag = defrag_filewise(&layout);
+        assert_eq!(
+            defrag.render(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(format!("{defrag:?}"), defrag.render());
+    }
+
+    #[test]
+    fn test_checksum_beyond_usize() {
+        let file = FileBlock {
+            id: 100_000,
+            start: 100_000,
+            count: 2,
+        };
+        let layout = Layout {
+            blocks: vec![file],
+            size: 100_002,
+        };
+        let checksum = compute_checksum(&layout);
+        assert!(checksum > u32::MAX as u128);
+        assert_eq!(checksum, 100_000 * 100_000 + 100_000 * 100_001);
+
+        // a single block product already exceeds usize, this wraps or panics with a usize sum
+        let file = FileBlock {
+            id: usize::MAX,
+            start: usize::MAX / 4,
+            count: 2,
+        };
+        let layout = Layout {
+            blocks: vec![file],
+            size: usize::MAX,
+        };
+        let checksum = compute_checksum(&layout);
+        let id = usize::MAX as u128;
+        let start = (usize::MAX / 4) as u128;
+        assert!(checksum > usize::MAX as u128);
+        assert_eq!(checksum, id * start + id * (start + 1));
+    }
 }
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Tile {
@@ -52,6 +55,10 @@ impl Tile {
             Tile::H9 => Err(AocError::ValueError("H9 is already highest".to_string())),
         }
     }
+
+    fn height(self) -> usize {
+        self as usize
+    }
 }
 
 struct Map {
@@ -74,47 +81,53 @@ impl Map {
     }
 }
 
-fn get_trailscore_recursive(
+// Depth First Search with an explicit stack, so long trails cannot overflow the call stack
+fn get_trailscore(
     x: usize,
     y: usize,
     map: &Map,
     expected: Tile,
+    end: Tile,
 ) -> (usize, HashSet<(usize, usize)>) {
     let mut set = HashSet::new();
-    if map.data[y][x] != expected {
-        return (0, set);
-    }
-    if expected == Tile::H9 {
-        set.insert((x, y));
-        return (1, set);
-    }
-
-    let next = expected.next().unwrap();
     let mut all_paths = 0;
-    if x > 0 {
-        let (paths, peaks) = get_trailscore_recursive(x - 1, y, map, next);
-        set.extend(peaks);
-        all_paths += paths;
-    }
-    if y > 0 {
-        let (paths, peaks) = get_trailscore_recursive(x, y - 1, map, next);
-        set.extend(peaks);
-        all_paths += paths;
-    }
-    if x < map.width - 1 {
-        let (paths, peaks) = get_trailscore_recursive(x + 1, y, map, next);
-        set.extend(peaks);
-        all_paths += paths;
-    }
-    if y < map.height - 1 {
-        let (paths, peaks) = get_trailscore_recursive(x, y + 1, map, next);
-        set.extend(peaks);
-        all_paths += paths;
+    let mut stack = vec![((x, y), expected)];
+    while let Some(((x, y), expected)) = stack.pop() {
+        if map.data[y][x] != expected {
+            continue;
+        }
+        if expected == end {
+            set.insert((x, y));
+            all_paths += 1;
+            continue;
+        }
+
+        let Ok(next) = expected.next() else {
+            continue;
+        };
+        if x > 0 {
+            stack.push(((x - 1, y), next));
+        }
+        if y > 0 {
+            stack.push(((x, y - 1), next));
+        }
+        if x < map.width - 1 {
+            stack.push(((x + 1, y), next));
+        }
+        if y < map.height - 1 {
+            stack.push(((x, y + 1), next));
+        }
     }
     (all_paths, set)
 }
 
 fn get_map_score_and_rating(map: &Map) -> (usize, usize) {
+    score_and_rating(map, Tile::H0, Tile::H9)
+}
+
+// trails climb one height per step from `start` up to `end`, with `start == end`
+// every matching cell is a trail of its own
+fn score_and_rating(map: &Map, start: Tile, end: Tile) -> (usize, usize) {
     if map.height == 0 || map.width == 0 {
         return (0, 0);
     }
@@ -123,7 +136,7 @@ fn get_map_score_and_rating(map: &Map) -> (usize, usize) {
     let mut rating = 0;
     for y in 0..map.height {
         for x in 0..map.width {
-            let (paths, set) = get_trailscore_recursive(x, y, map, Tile::H0);
+            let (paths, set) = get_trailscore(x, y, map, start, end);
             rating += paths;
             score += set.len();
         }
@@ -131,17 +144,84 @@ fn get_map_score_and_rating(map: &Map) -> (usize, usize) {
     (score, rating)
 }
 
-pub fn run() -> Result<()> {
+// number of distinct trails from every cell to any peak, filled from the peaks downwards
+fn map_rating_dp(map: &Map) -> usize {
+    let mut cells = (0..map.height)
+        .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|&(x, y)| std::cmp::Reverse(map.data[y][x].height()));
+
+    let mut paths = vec![vec![0; map.width]; map.height];
+    for (x, y) in cells {
+        let tile = map.data[y][x];
+        let Ok(next) = tile.next() else {
+            paths[y][x] = 1;
+            continue;
+        };
+        let mut neighbors = Vec::with_capacity(4);
+        if x > 0 {
+            neighbors.push((x - 1, y));
+        }
+        if y > 0 {
+            neighbors.push((x, y - 1));
+        }
+        if x < map.width - 1 {
+            neighbors.push((x + 1, y));
+        }
+        if y < map.height - 1 {
+            neighbors.push((x, y + 1));
+        }
+        paths[y][x] = neighbors
+            .into_iter()
+            .filter(|&(nx, ny)| map.data[ny][nx] == next)
+            .map(|(nx, ny)| paths[ny][nx])
+            .sum();
+    }
+
+    (0..map.height)
+        .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.data[y][x] == Tile::H0)
+        .map(|(x, y)| paths[y][x])
+        .sum()
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 10");
     let path = PathBuf::from("./resources/day10.txt");
     let data = util::get_data_string(&path)?;
     let map = Map::parse(&data)?;
-    let (score, rating) = get_map_score_and_rating(&map);
-    println!("trail score: {score}");
-    println!("trail rating: {rating}");
+    if part.one() {
+        let (score, _) = get_map_score_and_rating(&map);
+        println!("trail score: {score}");
+    }
+    if part.two() {
+        let rating = map_rating_dp(&map);
+        println!("trail rating: {rating}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        10
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let map = Map::parse(&data)?;
+        let (score, _) = get_map_score_and_rating(&map);
+        Ok(score.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let map = Map::parse(&data)?;
+        Ok(map_rating_dp(&map).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,6 +237,7 @@ mod tests {
         let (score, rating) = get_map_score_and_rating(&map);
         assert_eq!(score, 1);
         assert_eq!(rating, 1);
+        assert_eq!(map_rating_dp(&map), rating);
     }
 
     #[test]
@@ -170,5 +251,19 @@ mod tests {
         let (score, rating) = get_map_score_and_rating(&map);
         assert_eq!(score, 1);
         assert_eq!(rating, 2);
+        assert_eq!(map_rating_dp(&map), rating);
+    }
+
+    #[test]
+    fn test_sub_range() {
+        let data = "55556
+50547
+51238
+54389
+55675";
+        let map = Map::parse(data).unwrap();
+        assert_eq!(score_and_rating(&map, Tile::H2, Tile::H4), (2, 2));
+        assert_eq!(score_and_rating(&map, Tile::H8, Tile::H8), (2, 2));
+        assert_eq!(score_and_rating(&map, Tile::H4, Tile::H2), (0, 0));
     }
 }
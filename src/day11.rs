@@ -2,52 +2,37 @@ use anyhow::Result;
 use std::{
     collections::{hash_map::Entry, HashMap},
     ops::ControlFlow,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug)]
 struct Stone(usize);
 
+fn digits(v: usize) -> usize {
+    v.checked_ilog10().unwrap_or(0) as usize + 1
+}
+
 impl Stone {
-    fn blink(self) -> Vec<Self> {
+    fn blink(self) -> Result<Vec<Self>, AocError> {
         match self.0 {
-            0 => vec![Stone(1)],
-            v if util::is_even(v.to_string().len()) => {
-                let s = v.to_string();
-                let mid = s.len() / 2;
-                let (left, right) = s.split_at(mid);
-                let left = Stone::try_from(left).expect("can split");
-                let right = Stone::try_from(right).expect("can_split");
-                vec![left, right]
+            0 => Ok(vec![Stone(1)]),
+            v if util::is_even(digits(v)) => {
+                let split = 10usize.pow(digits(v) as u32 / 2);
+                Ok(vec![Stone(v / split), Stone(v % split)])
             }
-            v => vec![Stone(v * 2024)],
+            v => match v.checked_mul(2024) {
+                Some(v) => Ok(vec![Stone(v)]),
+                None => {
+                    let s = format!("stone {v} overflows when multiplied by 2024");
+                    Err(AocError::ValueError(s))
+                }
+            },
         }
     }
 }
 
-impl TryFrom<&str> for Stone {
-    type Error = AocError;
-
-    fn try_from(s: &str) -> std::result::Result<Self, Self::Error> {
-        if s.is_empty() {
-            let s = "can not make stone from empty str".to_string();
-            return Err(AocError::ValueError(s));
-        }
-        let s = s.trim_start_matches("00");
-        if s.is_empty() {
-            return Ok(Stone(0));
-        }
-
-        let v = s.parse::<usize>().map_err(|_| {
-            let s = format!("Could not parse to stone: {s}");
-            AocError::ValueError(s)
-        })?;
-        Ok(Stone(v))
-    }
-}
-
 fn parse_stones(input: &str) -> Result<Vec<Stone>> {
     input
         .split_whitespace()
@@ -56,12 +41,15 @@ fn parse_stones(input: &str) -> Result<Vec<Stone>> {
         .collect()
 }
 
-fn do_blinks(mut stones: Vec<Stone>, count: usize) -> Vec<Stone> {
+fn do_blinks(mut stones: Vec<Stone>, count: usize) -> Result<Vec<Stone>> {
     for _ in 0..count {
-        let test = stones.iter().map(|s| s.blink()).collect::<Vec<_>>();
+        let test = stones
+            .iter()
+            .map(|s| s.blink())
+            .collect::<Result<Vec<_>, _>>()?;
         stones = test.into_iter().flatten().collect();
     }
-    stones
+    Ok(stones)
 }
 
 #[derive(Debug)]
@@ -73,7 +61,7 @@ struct StackFrame {
 type Cache = HashMap<(usize, usize), usize>; // map (value, blinks) -> n_stones
 
 // find all split totals via Depth First Search
-fn expand_cache(stone: Stone, count: usize, cache: &mut Cache) {
+fn expand_cache(stone: Stone, count: usize, cache: &mut Cache) -> Result<()> {
     let mut stack = Vec::with_capacity(count);
 
     let initial_frame = StackFrame {
@@ -94,7 +82,7 @@ fn expand_cache(stone: Stone, count: usize, cache: &mut Cache) {
             continue;
         }
         if blinks_left == 1 {
-            let value = stone.blink().len();
+            let value = stone.blink()?.len();
             cache.entry((stone.0, 1)).insert_entry(value);
             stack.pop();
             continue;
@@ -102,7 +90,7 @@ fn expand_cache(stone: Stone, count: usize, cache: &mut Cache) {
 
         // check if we know the answer to our splits
         // if not, then we push to stack and get missing answer first
-        let total_splits = stone.blink().into_iter().try_fold(0, |acc, stone| {
+        let total_splits = stone.blink()?.into_iter().try_fold(0, |acc, stone| {
             let key = (stone.0, blinks_left - 1);
             match cache.entry(key) {
                 Entry::Vacant(_) => ControlFlow::Break(stone),
@@ -126,12 +114,14 @@ fn expand_cache(stone: Stone, count: usize, cache: &mut Cache) {
         cache.entry(key).insert_entry(total_splits);
         stack.pop();
     }
+    Ok(())
 }
 
-fn do_blinks_cached(stones: &Vec<Stone>, count: usize) -> usize {
+#[allow(dead_code)]
+fn do_blinks_cached(stones: &Vec<Stone>, count: usize) -> Result<usize> {
     let mut cache: Cache = HashMap::new(); // map (value, blinks) -> n_stones
     for &stone in stones {
-        expand_cache(stone, count, &mut cache);
+        expand_cache(stone, count, &mut cache)?;
     }
 
     let total = stones
@@ -143,36 +133,86 @@ fn do_blinks_cached(stones: &Vec<Stone>, count: usize) -> usize {
         })
         .sum();
 
-    total
+    Ok(total)
 }
 
-pub fn run() -> Result<()> {
+// only the number of stones per value matters, not their order
+fn blink_counts(stones: &[Stone], count: usize) -> Result<usize> {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for stone in stones {
+        *counts.entry(stone.0).or_default() += 1;
+    }
+    for _ in 0..count {
+        let mut next: HashMap<usize, usize> = HashMap::with_capacity(counts.len());
+        for (value, n) in counts {
+            for stone in Stone(value).blink()? {
+                *next.entry(stone.0).or_default() += n;
+            }
+        }
+        counts = next;
+    }
+    Ok(counts.values().sum())
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 11");
     let path = PathBuf::from("./resources/day11.txt");
     let data = util::get_data_string(&path)?;
     let stones_orig = parse_stones(&data)?;
-    let stones = do_blinks(stones_orig.clone(), 25);
-    println!("after 25 blinks we have: {} stones", stones.len());
-    let total = do_blinks_cached(&stones_orig, 75);
-    println!("after 75 blinks we have: {total} stones");
+    if part.one() {
+        let stones = do_blinks(stones_orig.clone(), 25)?;
+        println!("after 25 blinks we have: {} stones", stones.len());
+    }
+    if part.two() {
+        let total = blink_counts(&stones_orig, 75)?;
+        println!("after 75 blinks we have: {total} stones");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        11
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let stones = parse_stones(&data)?;
+        Ok(do_blinks(stones, 25)?.len().to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let stones = parse_stones(&data)?;
+        Ok(blink_counts(&stones, 75)?.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_stone_from_str() {
-        let s = "0";
-        let stone = Stone::try_from(s).unwrap();
-        assert_eq!(stone.0, 0);
-        let s = "000";
-        let stone = Stone::try_from(s).unwrap();
-        assert_eq!(stone.0, 0);
-        let s = "00000001";
-        let stone = Stone::try_from(s).unwrap();
-        assert_eq!(stone.0, 1);
+    fn test_parse_leading_zeros() {
+        let stones = parse_stones("0 000 00000001").unwrap();
+        let values: Vec<_> = stones.iter().map(|s| s.0).collect();
+        assert_eq!(values, vec![0, 0, 1]);
+        assert!(parse_stones("1 x").is_err());
+    }
+
+    #[test]
+    fn test_split_arithmetic() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(1000), 4);
+        let stones = Stone(1000).blink().unwrap();
+        assert_eq!(stones.len(), 2);
+        assert_eq!(stones[0].0, 10);
+        assert_eq!(stones[1].0, 0);
+        let stones = Stone(253000).blink().unwrap();
+        assert_eq!((stones[0].0, stones[1].0), (253, 0));
     }
 
     #[test]
@@ -180,7 +220,7 @@ mod tests {
         let input = "0 1 125 17 2002";
         let stones = parse_stones(input).unwrap();
         assert_eq!(stones.len(), 5);
-        let stones = do_blinks(stones, 1);
+        let stones = do_blinks(stones, 1).unwrap();
         assert_eq!(stones.len(), 7);
     }
 
@@ -189,7 +229,7 @@ mod tests {
         let input = "0 1 125 17 2002";
         let stones = parse_stones(input).unwrap();
         assert_eq!(stones.len(), 5);
-        let stones = do_blinks(stones, 2);
+        let stones = do_blinks(stones, 2).unwrap();
         assert_eq!(stones.len(), 10);
     }
 
@@ -198,9 +238,9 @@ mod tests {
         let input = "125 17";
         let stones = parse_stones(input).unwrap();
         assert_eq!(stones.len(), 2);
-        let stones = do_blinks(stones, 6);
+        let stones = do_blinks(stones, 6).unwrap();
         assert_eq!(stones.len(), 22);
-        let stones = do_blinks(stones, 25 - 6);
+        let stones = do_blinks(stones, 25 - 6).unwrap();
         assert_eq!(stones.len(), 55312);
     }
 
@@ -209,7 +249,39 @@ mod tests {
         let input = "125 17";
         let stones = parse_stones(input).unwrap();
         assert_eq!(stones.len(), 2);
-        let total = do_blinks_cached(&stones, 25);
+        let total = do_blinks_cached(&stones, 25).unwrap();
         assert_eq!(total, 55312);
     }
+
+    #[test]
+    fn test_example_counts() {
+        let input = "125 17";
+        let stones = parse_stones(input).unwrap();
+        assert_eq!(blink_counts(&stones, 25).unwrap(), 55312);
+        assert_eq!(
+            blink_counts(&stones, 75).unwrap(),
+            do_blinks_cached(&stones, 75).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multiply_overflow() {
+        // stones with an odd number of digits get multiplied
+        let odd_digits = |v| !util::is_even(digits(v));
+        let boundary = usize::MAX / 2024;
+        let fits = match odd_digits(boundary) {
+            true => boundary,
+            false => 10usize.pow(digits(boundary) as u32 - 1) - 1,
+        };
+        let too_large = match odd_digits(boundary + 1) {
+            true => boundary + 1,
+            false => 10usize.pow(digits(boundary) as u32),
+        };
+        assert!(odd_digits(fits) && odd_digits(too_large));
+
+        let stones = Stone(fits).blink().unwrap();
+        assert_eq!(stones[0].0, fits * 2024);
+        assert!(Stone(too_large).blink().is_err());
+        assert!(blink_counts(&[Stone(too_large)], 1).is_err());
+    }
 }
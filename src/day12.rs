@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{collections::VecDeque, path::PathBuf};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, Default)]
 struct Tile {
@@ -243,12 +246,15 @@ fn mark_side(pos: Pos, outside: Direction, plot: &mut Plot) -> usize {
     let mut sides = 0;
     let mut current_state = first_state;
 
+    // the walk is a cycle through distinct (tile, side) states,
+    // so it has to return to the start within this many steps
+    let max_steps = 4 * plot.width * plot.height;
     let mut init = true;
     let mut step = 0;
     while current_state != first_state || init {
         init = false;
         step += 1;
-        assert!(step < 10000);
+        assert!(step <= max_steps, "border walk did not return to start");
 
         let next_state = get_next_state(current_state, plot);
         if next_state.outside != current_state.outside {
@@ -312,15 +318,24 @@ struct BFSState {
     next: Option<Pos>,
 }
 
-fn get_bulk_price_at(pos: Pos, plot: &mut Plot) -> usize {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct RegionStats {
+    plant: char,
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+}
+
+fn get_region_at(pos: Pos, plot: &mut Plot) -> Option<RegionStats> {
     let Pos(x, y) = pos;
     assert!(x < plot.width && y < plot.height);
     let expected = match plot.data[y][x] {
-        Tile { visited, .. } if visited => return 0,
+        Tile { visited, .. } if visited => return None,
         Tile { plant, .. } => plant,
     };
 
     let mut sides = 0;
+    let mut perimeter = 0;
     let mut fields = 0;
 
     let mut queue = VecDeque::new();
@@ -336,12 +351,14 @@ fn get_bulk_price_at(pos: Pos, plot: &mut Plot) -> usize {
                 let last = last.expect("no longer start");
                 let new_sides = mark_all_sides(last, plot);
                 sides += new_sides;
+                perimeter += 1;
                 continue;
             }
             Some(Tile { plant, .. }) if *plant != expected => {
                 let last = last.expect("no longer start");
                 let new_sides = mark_all_sides(last, plot);
                 sides += new_sides;
+                perimeter += 1;
                 continue;
             }
             Some(Tile { visited, .. }) if !*visited => {
@@ -377,31 +394,124 @@ fn get_bulk_price_at(pos: Pos, plot: &mut Plot) -> usize {
         queue.push_back(down);
     }
 
-    sides * fields
+    Some(RegionStats {
+        plant: expected,
+        area: fields,
+        perimeter,
+        sides,
+    })
 }
 
-fn get_perimeter_price_bulk(mut plot: Plot) -> usize {
+fn regions(plot: &Plot) -> Vec<RegionStats> {
+    let mut plot = plot.clone();
+    let mut regions = Vec::new();
+    for y in 0..plot.height {
+        for x in 0..plot.width {
+            regions.extend(get_region_at(Pos(x, y), &mut plot));
+        }
+    }
+    regions
+}
+
+fn get_perimeter_price_bulk(plot: Plot) -> usize {
+    regions(&plot).iter().map(|r| r.area * r.sides).sum()
+}
+
+// a region has as many sides as it has corners, count them for each tile:
+// convex when both neighbors are foreign, concave when only the diagonal is
+fn count_corners(pos: Pos, plot: &Plot) -> usize {
+    let plant = plot.get(pos).expect("within plot").plant;
+    let same = |p: Option<Pos>| p.is_some_and(|p| plot.get(p).expect("checked").plant == plant);
+
+    let mut dir = Direction::Up;
+    let mut corners = 0;
+    for _ in 0..4 {
+        let side = dir.rotate();
+        let ahead = same(plot.move_pos(pos, dir));
+        let beside = same(plot.move_pos(pos, side));
+        let diagonal = same(plot.move_pos(pos, dir).and_then(|p| plot.move_pos(p, side)));
+        if (!ahead && !beside) || (ahead && beside && !diagonal) {
+            corners += 1;
+        }
+        dir = side;
+    }
+    corners
+}
+
+#[allow(dead_code)]
+fn bulk_price_corners(plot: &Plot) -> usize {
+    let mut visited = vec![vec![false; plot.width]; plot.height];
     let mut price = 0;
     for y in 0..plot.height {
         for x in 0..plot.width {
-            price += get_bulk_price_at(Pos(x, y), &mut plot);
+            if visited[y][x] {
+                continue;
+            }
+            let plant = plot.data[y][x].plant;
+            let mut area = 0;
+            let mut corners = 0;
+            let mut stack = vec![Pos(x, y)];
+            visited[y][x] = true;
+            while let Some(pos) = stack.pop() {
+                area += 1;
+                corners += count_corners(pos, plot);
+                for dir in [
+                    Direction::Up,
+                    Direction::Right,
+                    Direction::Down,
+                    Direction::Left,
+                ] {
+                    let Some(next @ Pos(nx, ny)) = plot.move_pos(pos, dir) else {
+                        continue;
+                    };
+                    if !visited[ny][nx] && plot.data[ny][nx].plant == plant {
+                        visited[ny][nx] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            price += area * corners;
         }
     }
     price
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 12");
     let path = PathBuf::from("./resources/day12.txt");
     let data = util::get_data_string(&path)?;
     let plot = parse_plot(&data)?;
-    let price = get_perimeter_price(plot.clone());
-    println!("price for perimeter: {price}");
-    let bulk_price = get_perimeter_price_bulk(plot);
-    println!("price for perimeter in bulk: {bulk_price}");
+    if part.one() {
+        let price = get_perimeter_price(plot.clone());
+        println!("price for perimeter: {price}");
+    }
+    if part.two() {
+        let bulk_price = get_perimeter_price_bulk(plot);
+        println!("price for perimeter in bulk: {bulk_price}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        12
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let plot = parse_plot(&data)?;
+        Ok(get_perimeter_price(plot).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let plot = parse_plot(&data)?;
+        Ok(get_perimeter_price_bulk(plot).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,6 +546,7 @@ BBCD
 BBCC
 EEEC";
         let plot = parse_plot(input).unwrap();
+        assert_eq!(bulk_price_corners(&plot), 80);
         let price = get_perimeter_price_bulk(plot);
         assert_eq!(price, 80);
     }
@@ -448,7 +559,63 @@ OOOOO
 OXOXO
 OOOOO";
         let plot = parse_plot(input).unwrap();
+        assert_eq!(bulk_price_corners(&plot), 436);
         let price = get_perimeter_price_bulk(plot);
         assert_eq!(price, 436);
     }
+
+    #[test]
+    fn test_simple_plot_regions() {
+        let input = "AAAA
+BBCD
+BBCC
+EEEC";
+        let plot = parse_plot(input).unwrap();
+        let regions = regions(&plot);
+        assert_eq!(regions.len(), 5);
+        let c = regions.iter().find(|r| r.plant == 'C').unwrap();
+        assert_eq!((c.area, c.perimeter, c.sides), (4, 10, 8));
+        let price = regions.iter().map(|r| r.area * r.perimeter).sum::<usize>();
+        assert_eq!(price, 140);
+        let price = regions.iter().map(|r| r.area * r.sides).sum::<usize>();
+        assert_eq!(price, 80);
+    }
+
+    fn spiral(n: usize) -> String {
+        let mut grid = vec![vec!['.'; n]; n];
+        let dirs = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        let (mut x, mut y) = (0isize, 0isize);
+        grid[0][0] = 'A';
+        for i in 0.. {
+            let len = match i {
+                0 => n - 1,
+                i => (n - 1).saturating_sub(2 * ((i - 1) / 2)),
+            };
+            if len < 2 {
+                break;
+            }
+            let (dx, dy) = dirs[i % 4];
+            for _ in 0..len {
+                x += dx;
+                y += dy;
+                grid[y as usize][x as usize] = 'A';
+            }
+        }
+        grid.into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_spiral_bulk() {
+        // the border walk takes one step per fence segment, and this spiral
+        // has more than the 10000 segments the old step limit allowed for
+        let plot = parse_plot(&spiral(121)).unwrap();
+        let spiral_region = regions(&plot).into_iter().find(|r| r.plant == 'A').unwrap();
+        assert_eq!(spiral_region.perimeter, 14884);
+        assert_eq!(get_perimeter_price(plot.clone()), 214446244);
+        assert_eq!(bulk_price_corners(&plot), 3543604);
+        assert_eq!(get_perimeter_price_bulk(plot), 3543604);
+    }
 }
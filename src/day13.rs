@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{collections::BinaryHeap, path::PathBuf};
+use std::{
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Pos(usize, usize);
@@ -110,15 +113,8 @@ fn do_best_play(machine: Machine) -> Option<usize> {
     };
     priority_queue.push(initial_node);
 
-    const MAX_ITER: usize = 101 * 101;
-    let mut iter = 0;
-    while !priority_queue.is_empty() {
-        if iter > MAX_ITER {
-            panic!("too many iterations");
-        }
-        iter += 1;
-
-        let top = priority_queue.pop().expect("checked not empty");
+    // each button is pressed at most 100 times, so the search space is finite
+    while let Some(top) = priority_queue.pop() {
         let Node {
             tokens,
             pos,
@@ -173,100 +169,82 @@ fn find_optimal_cost_dijkstra(machines: &Vec<Machine>) -> (usize, usize) {
     (tokens, prizes)
 }
 
-fn check_steps_close_to(
-    machine: Machine,
-    expensive_steps: usize,
-    cheap_steps: usize,
-) -> Option<(usize, usize)> {
+// number of presses for button A and button B that reach the prize
+fn solve_machine(machine: Machine) -> Option<(usize, usize)> {
     let Machine {
         button_a,
         button_b,
         prize,
     } = machine;
-    let Movement(expensive_x, expensive_y) = button_a;
-    let Movement(cheap_x, cheap_y) = button_b;
-
-    for j in 0..3 {
-        if j == 0 && expensive_steps == 0 {
-            continue;
-        };
-        let test_expensive = expensive_steps + j - 1;
-        for i in 0..3 {
-            if i == 0 && cheap_steps == 0 {
-                continue;
-            };
-            let test_cheap = cheap_steps + i - 1;
+    let Movement(ax, ay) = button_a;
+    let Movement(bx, by) = button_b;
+    let Pos(tx, ty) = prize;
+    let [ax, ay, bx, by, tx, ty] = [ax, ay, bx, by, tx, ty].map(|v| v as i128);
+
+    // a * ax + b * bx = tx
+    // a * ay + b * by = ty
+    let det = ax * by - ay * bx;
+    if det == 0 {
+        // edge case: both buttons move in the same direction
+        return solve_collinear([ax, ay, bx, by, tx, ty]);
+    }
 
-            let got_x = test_cheap * cheap_x + test_expensive * expensive_x;
-            let got_y = test_cheap * cheap_y + test_expensive * expensive_y;
-            let got = Pos(got_x, got_y);
+    // Cramer's rule, only whole presses count
+    let a = tx * by - ty * bx;
+    let b = ax * ty - ay * tx;
+    if a % det != 0 || b % det != 0 {
+        return None;
+    }
+    let (a, b) = (a / det, b / det);
+    if a < 0 || b < 0 {
+        return None;
+    }
+    Some((a as usize, b as usize))
+}
 
-            if got == prize {
-                return Some((test_expensive, test_cheap));
-            }
-        }
+// returns (gcd, x, y) with a * x + b * y = gcd
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
     }
-    None
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
 }
 
-fn check_machine(machine: Machine) -> Option<usize> {
-    let Machine {
-        button_a,
-        button_b,
-        prize,
-    } = machine;
-    let Movement(expensive_x, expensive_y) = button_a;
-    let Movement(cheap_x, cheap_y) = button_b;
-    let Pos(target_x, target_y) = prize;
-
-    let (expensive_steps, cheap_steps) = if button_a == button_b {
-        // edge case: both buttons move the same
-        // columns are linearly depentent, choose to only move with cheap steps
-
-        // solve i * ax = tx
-        // solve i * ay = ty
-        let ax = cheap_x as f64;
-        let bx = cheap_y as f64;
-        let tx = target_x as f64;
-        let ty = target_y as f64;
-        let i = tx / ax;
-        let j = ty / bx;
-
-        let i = i.round() as usize;
-        let j = j.round() as usize;
-        if i.abs_diff(j) > 1 {
-            return None;
-        }
-        (0, i)
+// with linearly dependent buttons the solutions form a family along one line,
+// the cost changes linearly along it, so the cheapest is at one of its ends
+fn solve_collinear([ax, ay, bx, by, tx, ty]: [i128; 6]) -> Option<(usize, usize)> {
+    // one axis decides, the candidates are checked against both afterwards
+    let (p, q, t) = if ax != 0 || bx != 0 {
+        (ax, bx, tx)
     } else {
-        // at least one button does not move diagonal
-        // two equations with two variables -> unique solution
-        let ax = cheap_x as f64;
-        let ay = cheap_y as f64;
-        let bx = expensive_x as f64;
-        let by = expensive_y as f64;
-        let tx = target_x as f64;
-        let ty = target_y as f64;
-        // i * ax + j * bx = tx
-        // i * ay + j * by = ty
-        let by = by - (ay / ax) * bx;
-        let ty = ty - (ay / ax) * tx;
-        // i * ax + j * bx = tx
-        //          j * by = ty
-        let ty = ty / by;
-        // i * ax + j * bx = tx
-        //          j      = ty
-        let tx = tx - bx * ty;
-        // i * ax          = tx
-        //          j      = ty
-        let tx = tx / ax;
-        // i               = tx
-        //          j      = ty
-
-        (ty.round() as usize, tx.round() as usize)
+        (ay, by, ty)
     };
+    let candidates = match (p, q) {
+        (0, 0) => vec![(0, 0)],
+        (0, q) => vec![(0, t / q)],
+        (p, 0) => vec![(t / p, 0)],
+        (p, q) => {
+            let (g, x, y) = extended_gcd(p, q);
+            if t % g != 0 {
+                return None;
+            }
+            // fewest A presses and fewest B presses
+            let a = (x * (t / g)).rem_euclid(q / g);
+            let b = (y * (t / g)).rem_euclid(p / g);
+            vec![(a, (t - a * p) / q), ((t - b * q) / p, b)]
+        }
+    };
+    candidates
+        .into_iter()
+        .filter(|&(a, b)| a >= 0 && b >= 0)
+        .filter(|&(a, b)| a * ax + b * bx == tx && a * ay + b * by == ty)
+        .min_by_key(|&(a, b)| 3 * a + b)
+        .map(|(a, b)| (a as usize, b as usize))
+}
 
-    check_steps_close_to(machine, expensive_steps, cheap_steps).map(|(a, b)| a * 3 + b)
+fn check_machine(machine: Machine) -> Option<usize> {
+    solve_machine(machine).map(|(a, b)| a * 3 + b)
 }
 
 fn find_optimal_cost_equation(machines: &Vec<Machine>) -> (usize, usize) {
@@ -281,20 +259,46 @@ fn find_optimal_cost_equation(machines: &Vec<Machine>) -> (usize, usize) {
     (tokens, prizes)
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 13");
     let path = PathBuf::from("./resources/day13.txt");
     let data = util::get_data_string(&path)?;
     let machines = parse_machines(&data)?;
-    // let (tokens, prizes) = find_optimal_cost_dijkstra(&machines);
-    let (tokens, prizes) = find_optimal_cost_equation(&machines);
-    println!("need {tokens} tokens to win {prizes} prizes");
-    let machines = correct_machines(machines);
-    let (tokens, prizes) = find_optimal_cost_equation(&machines);
-    println!("after correction, need {tokens} tokens to win {prizes} prizes");
+    if part.one() {
+        // let (tokens, prizes) = find_optimal_cost_dijkstra(&machines);
+        let (tokens, prizes) = find_optimal_cost_equation(&machines);
+        println!("need {tokens} tokens to win {prizes} prizes");
+    }
+    if part.two() {
+        let machines = correct_machines(machines);
+        let (tokens, prizes) = find_optimal_cost_equation(&machines);
+        println!("after correction, need {tokens} tokens to win {prizes} prizes");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        13
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let machines = parse_machines(&data)?;
+        let (tokens, _) = find_optimal_cost_equation(&machines);
+        Ok(tokens.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let machines = correct_machines(parse_machines(&data)?);
+        let (tokens, _) = find_optimal_cost_equation(&machines);
+        Ok(tokens.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,6 +314,60 @@ Prize: X=8400, Y=5400";
         assert_eq!(tokens, 280);
     }
 
+    #[test]
+    fn test_solve_machine() {
+        let input = "Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400";
+        let machine = parse_machines(input).unwrap();
+        assert_eq!(solve_machine(machine[0]), Some((80, 40)));
+        assert_eq!(check_machine(machine[0]), Some(280));
+    }
+
+    #[test]
+    fn test_collinear_buttons() {
+        let machine = |a: (usize, usize), b: (usize, usize), prize: (usize, usize)| Machine {
+            button_a: Movement(a.0, a.1),
+            button_b: Movement(b.0, b.1),
+            prize: Pos(prize.0, prize.1),
+        };
+        // only a mix of both buttons reaches the prize
+        let m = machine((2, 2), (3, 3), (5, 5));
+        assert_eq!(solve_machine(m), Some((1, 1)));
+        assert_eq!(check_machine(m), Some(4));
+        // A covers more than three times the distance of B, so it is cheaper
+        let m = machine((4, 4), (1, 1), (8, 8));
+        assert_eq!(solve_machine(m), Some((2, 0)));
+        assert_eq!(check_machine(m), Some(6));
+        // B does not move along x, A alone reaches the prize
+        let m = machine((0, 5), (0, 1), (0, 10));
+        assert_eq!(solve_machine(m), Some((2, 0)));
+        let m = machine((3, 3), (0, 0), (9, 9));
+        assert_eq!(solve_machine(m), Some((3, 0)));
+        let m = machine((3, 3), (2, 2), (1, 1));
+        assert_eq!(solve_machine(m), None);
+        let m = machine((0, 2), (0, 4), (0, 7));
+        assert_eq!(solve_machine(m), None);
+    }
+
+    #[test]
+    fn test_collinear_far_prize() {
+        let input = "Button A: X+1, Y+1
+Button B: X+2, Y+2
+Prize: X=7, Y=7
+
+Button A: X+5, Y+10
+Button B: X+1, Y+2
+Prize: X=0, Y=1";
+        let machines = correct_machines(parse_machines(input).unwrap());
+        // B covers twice the distance for a third of the cost
+        assert_eq!(solve_machine(machines[0]), Some((1, 5000000000003)));
+        // the prize is off the line both buttons move along
+        assert_eq!(solve_machine(machines[1]), None);
+        let (tokens, prizes) = find_optimal_cost_equation(&machines);
+        assert_eq!((tokens, prizes), (5000000000006, 1));
+    }
+
     #[test]
     fn test_unwinnable_machine() {
         let input = "Button A: X+26, Y+66
@@ -321,6 +379,15 @@ Prize: X=12748, Y=12176";
         assert_eq!(tokens, 0);
     }
 
+    #[test]
+    fn test_unreachable_within_presses() {
+        let input = "Button A: X+1, Y+1
+Button B: X+1, Y+1
+Prize: X=500, Y=500";
+        let machine = parse_machines(input).unwrap();
+        assert_eq!(do_best_play(machine[0]), None);
+    }
+
     #[test]
     fn test_two_machines() {
         let input = "Button A: X+94, Y+34
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use std::{char, fmt::Write, path::PathBuf};
+use std::{
+    char,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Pos(usize, usize);
@@ -26,13 +30,29 @@ impl Robot {
         let Pos(px, py) = self.pos;
         let Velocity(vx, vy) = self.vel;
         let Pos(mx, my) = max;
+        // rem_euclid wraps velocities of any magnitude into the grid
         self.pos = Pos(
-            (px + (mx as isize + vx) as usize) % mx,
-            (py + (my as isize + vy) as usize) % my,
+            (px as isize + vx).rem_euclid(mx as isize) as usize,
+            (py as isize + vy).rem_euclid(my as isize) as usize,
         );
     }
 }
 
+impl Map {
+    fn to_ppm(&self) -> Vec<u8> {
+        let Pos(mx, my) = self.size;
+        let mut occupied = vec![vec![false; mx]; my];
+        for robot in self.robots.iter() {
+            let Pos(x, y) = robot.pos;
+            occupied[y][x] = true;
+        }
+        util::render_ppm(mx, my, |x, y| match occupied[y][x] {
+            true => [0, 160, 0],
+            false => [0, 0, 0],
+        })
+    }
+}
+
 impl std::fmt::Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Pos(mx, my) = self.size;
@@ -55,9 +75,7 @@ impl std::fmt::Display for Map {
 }
 
 fn parse_robo_map(input: &str, size: Pos) -> Result<Map> {
-    let robots = input
-        .split("\n")
-        .filter(|s| !s.is_empty())
+    let robots = util::lines(input)
         .map(|s| {
             let Some((pos, vel)) = s.trim().split_once(" ") else {
                 return Err(AocError::ParseError.into());
@@ -69,8 +87,10 @@ fn parse_robo_map(input: &str, size: Pos) -> Result<Map> {
             let px = px.parse().map_err(|_| AocError::ParseError)?;
             let py = py.parse().map_err(|_| AocError::ParseError)?;
             let pos = Pos(px, py);
-            if px >= 101 || py >= 103 {
-                return Err(AocError::ParseError.into());
+            let Pos(mx, my) = size;
+            if px >= mx || py >= my {
+                let s = format!("robot at {px},{py} is outside of {mx}x{my} grid");
+                return Err(AocError::ValueError(s).into());
             }
 
             let vel = vel.strip_prefix("v=").ok_or(AocError::ParseError)?;
@@ -94,32 +114,62 @@ fn simulate_steps(mut map: Map, steps: usize) -> Map {
     map
 }
 
-fn calculate_safety_factor(map: &Map) -> usize {
-    let mut upper_left = 0;
-    let mut upper_right = 0;
-    let mut lower_left = 0;
-    let mut lower_right = 0;
+// robots in the upper-left, upper-right, lower-left and lower-right quadrant,
+// the middle row and column belong to no quadrant
+fn quadrant_counts(map: &Map) -> [usize; 4] {
+    let mut counts = [0; 4];
+    let Pos(mx, my) = map.size;
+    let mx = mx / 2;
+    let my = my / 2;
 
     for robot in map.robots.iter() {
         let Pos(x, y) = robot.pos;
-        let Pos(mx, my) = map.size;
-        let mx = mx / 2;
-        let my = my / 2;
         if x < mx && y < my {
-            upper_left += 1;
+            counts[0] += 1;
         }
         if x > mx && y < my {
-            upper_right += 1;
+            counts[1] += 1;
         }
         if x < mx && y > my {
-            lower_left += 1;
+            counts[2] += 1;
         }
         if x > mx && y > my {
-            lower_right += 1;
+            counts[3] += 1;
         }
     }
 
-    upper_left * upper_right * lower_left * lower_right
+    counts
+}
+
+fn calculate_safety_factor(map: &Map) -> usize {
+    quadrant_counts(map).iter().product()
+}
+
+fn all_positions_unique(map: &Map, occupied: &mut [bool]) -> bool {
+    let Pos(mx, _) = map.size;
+    occupied.fill(false);
+    for robot in map.robots.iter() {
+        let Pos(x, y) = robot.pos;
+        if std::mem::replace(&mut occupied[y * mx + x], true) {
+            return false;
+        }
+    }
+    true
+}
+
+// the picture is drawn by the first arrangement without overlapping robots,
+// positions repeat after width * height steps so there is nothing to find beyond that
+fn find_tree_step(map: &Map) -> Option<usize> {
+    let Pos(mx, my) = map.size;
+    let mut map = map.clone();
+    let mut occupied = vec![false; mx * my];
+    for step in 0..mx * my {
+        if all_positions_unique(&map, &mut occupied) {
+            return Some(step);
+        }
+        map.robots.iter_mut().for_each(|r| r.step(map.size));
+    }
+    None
 }
 
 #[allow(dead_code)]
@@ -130,6 +180,10 @@ fn search_for_christmas_tree(mut map: Map) {
     for step in (23..).step_by(101) {
         println!("step {step}:");
         println!("{map}");
+        let frame = format!("day14_step{step}.ppm");
+        if let Err(e) = std::fs::write(&frame, map.to_ppm()) {
+            println!("could not write {frame}: {e}");
+        }
         match std::io::stdin().read_line(&mut buf) {
             Err(_) => break,
             Ok(n) if n != 1 => break,
@@ -139,23 +193,51 @@ fn search_for_christmas_tree(mut map: Map) {
     }
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 14");
     let path = PathBuf::from("./resources/day14.txt");
     let data = util::get_data_string(&path)?;
     let max = Pos(101, 103);
     let map = parse_robo_map(&data, max)?;
-    let map_sim = simulate_steps(map.clone(), 100);
-    let safety_factor = calculate_safety_factor(&map_sim);
-    println!("safety factor: {safety_factor}");
-    // search_for_christmas_tree(map);
-    let until_tree = 7093;
-    let map_tree = simulate_steps(map, until_tree);
-    println!("at step {until_tree} we find the christmas tree:");
-    println!("{map_tree}");
+    if part.one() {
+        let map_sim = simulate_steps(map.clone(), 100);
+        let safety_factor = calculate_safety_factor(&map_sim);
+        println!("safety factor: {safety_factor}");
+    }
+    if part.two() {
+        // search_for_christmas_tree(map.clone());
+        let until_tree = find_tree_step(&map)
+            .ok_or_else(|| AocError::ValueError("robots never form a tree".to_string()))?;
+        let map_tree = simulate_steps(map, until_tree);
+        println!("at step {until_tree} we find the christmas tree:");
+        println!("{map_tree}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        14
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let map = parse_robo_map(&data, Pos(101, 103))?;
+        let map_sim = simulate_steps(map, 100);
+        Ok(calculate_safety_factor(&map_sim).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let map = parse_robo_map(&data, Pos(101, 103))?;
+        let until_tree = find_tree_step(&map)
+            .ok_or_else(|| AocError::ValueError("robots never form a tree".to_string()))?;
+        Ok(until_tree.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +252,54 @@ mod tests {
         assert_eq!(robot.pos, Pos(100, 102));
     }
 
+    #[test]
+    fn test_robot_movement_large_velocity() {
+        let mut robot = Robot {
+            pos: Pos(0, 0),
+            vel: Velocity(-150, 0),
+        };
+        robot.step(Pos(11, 7));
+        assert_eq!(robot.pos, Pos(4, 0));
+        robot.vel = Velocity(150, -15);
+        robot.step(Pos(11, 7));
+        assert_eq!(robot.pos, Pos(0, 6));
+    }
+
+    #[test]
+    fn test_to_ppm() {
+        let robot = Robot {
+            pos: Pos(1, 1),
+            vel: Velocity(0, 0),
+        };
+        let map = Map {
+            robots: vec![robot],
+            size: Pos(3, 3),
+        };
+        let ppm = map.to_ppm();
+        let header = b"P6\n3 3\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 3 * 3 * 3);
+        let center = header.len() + (3 + 1) * 3;
+        assert_eq!(ppm[center..center + 3], [0, 160, 0]);
+        assert_eq!(ppm[header.len()..header.len() + 3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        assert!(parse_robo_map("p=10,6 v=1,1", Pos(11, 7)).is_ok());
+        assert!(parse_robo_map("p=11,0 v=1,1", Pos(11, 7)).is_err());
+        assert!(parse_robo_map("p=0,7 v=1,1", Pos(11, 7)).is_err());
+        assert!(parse_robo_map("p=100,102 v=1,1", Pos(101, 103)).is_ok());
+    }
+
+    #[test]
+    fn test_find_tree_step() {
+        let path = PathBuf::from("./resources/day14.txt");
+        let data = util::get_data_string(&path).unwrap();
+        let map = parse_robo_map(&data, Pos(101, 103)).unwrap();
+        assert_eq!(find_tree_step(&map), Some(7093));
+    }
+
     #[test]
     fn test_example() {
         let s = "p=0,4 v=3,-3
@@ -187,6 +317,9 @@ p=9,5 v=-3,-3";
         let max = Pos(11, 7);
         let robots = parse_robo_map(s, max).unwrap();
         let robots = simulate_steps(robots, 100);
+        let counts = quadrant_counts(&robots);
+        assert_eq!(counts, [1, 3, 4, 1]);
+        assert_eq!(counts.iter().product::<usize>(), 12);
         let factor = calculate_safety_factor(&robots);
         assert_eq!(factor, 12);
     }
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use std::{fmt::Write, path::PathBuf};
+use std::{
+    fmt::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Direction {
@@ -41,6 +45,8 @@ struct Warehouse {
     size: Pos,
     robot: Pos,
     tiles: Vec<Vec<Tile>>,
+    // every step with the tiles it overwrote, in the order they were written
+    history: Vec<(Direction, Vec<(Pos, Tile)>)>,
 }
 
 impl Warehouse {
@@ -65,14 +71,20 @@ impl Warehouse {
         }
     }
 
+    // appends the boxes pushed by the layer `moving[layer]` to `moving`,
     // returns None if any position is blocked by a wall
-    fn find_pushed_boxes(&self, dir: Direction, pushing: &Vec<Pos>) -> Option<Vec<Pos>> {
+    fn find_pushed_boxes(
+        &self,
+        dir: Direction,
+        moving: &mut Vec<Pos>,
+        layer: Range<usize>,
+    ) -> Option<()> {
         // push in ascending order (x-coord) per row
-        let mut pushed = Vec::new();
         // keep track of smallest index so that we do not duplicate work later (diamond problem)
         let mut smallest = None;
 
-        for &pos in pushing {
+        for index in layer {
+            let pos = moving[index];
             let Some(neighbor) = self.neighbor_pos(pos, dir) else {
                 return None; // can not push box out of bounds
             };
@@ -104,49 +116,69 @@ impl Warehouse {
             }
             smallest = box2.map(|box2| box2.0).or(Some(box1.0));
 
-            pushed.push(box1);
+            moving.push(box1);
             if let Some(box2) = box2 {
-                pushed.push(box2);
+                moving.push(box2);
             }
         }
-        Some(pushed)
+        Some(())
     }
 
-    fn move_and_push_boxes(&mut self, dir: Direction) -> bool {
-        let mut pushed_rows = Vec::new();
-
-        let mut pushing = vec![self.robot];
-        pushed_rows.push(pushing.clone());
-
-        while !pushing.is_empty() {
-            let Some(pushed) = self.find_pushed_boxes(dir, &pushing) else {
-                return false;
-            };
-            pushed_rows.push(pushed.clone());
-            pushing = pushed;
+    // returns the overwritten tiles, or None if nothing could move
+    fn move_and_push_boxes(&mut self, dir: Direction) -> Option<Vec<(Pos, Tile)>> {
+        // all moving tiles layer by layer, each layer pushed by the one before
+        let mut moving = vec![self.robot];
+        let mut layer_start = 0;
+        while layer_start < moving.len() {
+            let layer_end = moving.len();
+            self.find_pushed_boxes(dir, &mut moving, layer_start..layer_end)?;
+            layer_start = layer_end;
         }
 
-        for row in pushed_rows.iter().rev() {
-            for &pos in row {
-                let tile = self.at(pos);
-                let empty = self.neighbor_pos(pos, dir).unwrap();
-                // println!("push {tile} from {pos:?} to {empty:?}");
-                self.set(empty, tile);
-                self.set(pos, Tile::Empty);
-                if let Tile::Robot = tile {
-                    self.robot = empty;
-                }
+        // the far side moves first, so every tile moves into an empty spot
+        let mut overwritten = Vec::new();
+        for &pos in moving.iter().rev() {
+            let tile = self.at(pos);
+            let empty = self.neighbor_pos(pos, dir).unwrap();
+            util::log!("push {tile} from {pos:?} to {empty:?}");
+            overwritten.push((empty, self.at(empty)));
+            self.set(empty, tile);
+            overwritten.push((pos, tile));
+            self.set(pos, Tile::Empty);
+            if let Tile::Robot = tile {
+                self.robot = empty;
             }
         }
 
-        true
+        Some(overwritten)
+    }
+
+    // returns whether the robot moved, blocked steps are recorded as well
+    fn step(&mut self, dir: Direction) -> bool {
+        let overwritten = self.move_and_push_boxes(dir);
+        let moved = overwritten.is_some();
+        self.history.push((dir, overwritten.unwrap_or_default()));
+        moved
+    }
+
+    // reverts the last step and returns its direction
+    #[allow(dead_code)]
+    fn undo(&mut self) -> Option<Direction> {
+        let (dir, overwritten) = self.history.pop()?;
+        for &(pos, tile) in overwritten.iter().rev() {
+            self.set(pos, tile);
+            if let Tile::Robot = tile {
+                self.robot = pos;
+            }
+        }
+        Some(dir)
     }
 
     fn execute_protocol(&mut self, moves: &[Direction]) {
-        // println!("start:\n{self}");
+        util::log!("start:\n{self}");
         for &dir in moves.iter() {
-            self.move_and_push_boxes(dir);
-            // println!("with {dir:?}:\n{self}");
+            self.step(dir);
+            util::log!("with {dir:?}:\n{self}");
         }
     }
 
@@ -181,54 +213,8 @@ impl std::fmt::Display for Warehouse {
     }
 }
 
-fn parse_small_warehouse(input: &str) -> Result<(Warehouse, Vec<Direction>)> {
-    let mut split_iter = input.split("\n\n");
-    let Some(tiles) = split_iter.next() else {
-        return Err(AocError::ParseError.into());
-    };
-    let Some(moves) = split_iter.next() else {
-        return Err(AocError::ParseError.into());
-    };
-    if split_iter.next().is_some() {
-        return Err(AocError::ParseError.into());
-    };
-
-    let moves = moves
-        .chars()
-        .filter(|&c| c != '\n')
-        .map(|c| match c {
-            '^' => Ok(Direction::Up),
-            '>' => Ok(Direction::Right),
-            'v' => Ok(Direction::Down),
-            '<' => Ok(Direction::Left),
-            _ => Err(AocError::ParseError),
-        })
-        .collect::<Result<Vec<_>, AocError>>()?;
-
-    let mut robot_at = None;
-    let parser = |x, y, c| match c {
-        '.' => Ok(Tile::Empty),
-        '#' => Ok(Tile::Wall),
-        'O' => Ok(Tile::SmallBox),
-        '@' if robot_at.is_none() => {
-            robot_at = Some(Pos(x, y));
-            Ok(Tile::Robot)
-        }
-        _ => Err(AocError::ParseError),
-    };
-    let tiles = util::parse_tiles(tiles, parser)?;
-
-    let Some(robot) = robot_at else {
-        return Err(AocError::ParseError.into());
-    };
-    // when we know where the robot is, we know we have at least one row
-    let size = Pos(tiles[0].len(), tiles.len());
-
-    let warehouse = Warehouse { size, robot, tiles };
-    Ok((warehouse, moves))
-}
-
-fn parse_big_warehouse(input: &str) -> Result<(Warehouse, Vec<Direction>)> {
+// with `widen` every character becomes two tiles and boxes become big boxes
+fn parse_warehouse(input: &str, widen: bool) -> Result<(Warehouse, Vec<Direction>)> {
     let mut split_iter = input.split("\n\n");
     let Some(tiles) = split_iter.next() else {
         return Err(AocError::ParseError.into());
@@ -252,14 +238,16 @@ fn parse_big_warehouse(input: &str) -> Result<(Warehouse, Vec<Direction>)> {
         })
         .collect::<Result<Vec<_>, AocError>>()?;
 
-    // parser creates 2 tiles per character
+    // parser creates 2 tiles per character, only the first is kept when not widening
+    let scale = if widen { 2 } else { 1 };
     let mut robot_at = None;
     let parser = |x, y, c| match c {
         '.' => Ok([Tile::Empty, Tile::Empty]),
         '#' => Ok([Tile::Wall, Tile::Wall]),
-        'O' => Ok([Tile::BigBoxL, Tile::BigBoxR]),
+        'O' if widen => Ok([Tile::BigBoxL, Tile::BigBoxR]),
+        'O' => Ok([Tile::SmallBox, Tile::Empty]),
         '@' if robot_at.is_none() => {
-            robot_at = Some(Pos(x * 2, y));
+            robot_at = Some(Pos(x * scale, y));
             Ok([Tile::Robot, Tile::Empty])
         }
         _ => Err(AocError::ParseError),
@@ -268,7 +256,11 @@ fn parse_big_warehouse(input: &str) -> Result<(Warehouse, Vec<Direction>)> {
     // flatten lowest level
     let tiles: Vec<Vec<_>> = tiles
         .into_iter()
-        .map(|r| r.into_iter().flatten().collect())
+        .map(|r| {
+            r.into_iter()
+                .flat_map(|t| t.into_iter().take(scale))
+                .collect()
+        })
         .collect();
 
     let Some(robot) = robot_at else {
@@ -277,25 +269,56 @@ fn parse_big_warehouse(input: &str) -> Result<(Warehouse, Vec<Direction>)> {
     // when we know where the robot is, we know we have at least one row
     let size = Pos(tiles[0].len(), tiles.len());
 
-    let warehouse = Warehouse { size, robot, tiles };
+    let warehouse = Warehouse {
+        size,
+        robot,
+        tiles,
+        history: Vec::new(),
+    };
     Ok((warehouse, moves))
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 15");
     let path = PathBuf::from("./resources/day15.txt");
     let data = util::get_data_string(&path)?;
-    let (mut warehouse, moves) = parse_small_warehouse(&data)?;
-    warehouse.execute_protocol(&moves);
-    let gps_sum = warehouse.compute_gps_sum();
-    println!("GPS sum small warehouse: {gps_sum}");
-    let (mut warehouse, moves) = parse_big_warehouse(&data)?;
-    warehouse.execute_protocol(&moves);
-    let gps_sum = warehouse.compute_gps_sum();
-    println!("GPS sum big warehouse: {gps_sum}");
+    if part.one() {
+        let (mut warehouse, moves) = parse_warehouse(&data, false)?;
+        warehouse.execute_protocol(&moves);
+        let gps_sum = warehouse.compute_gps_sum();
+        println!("GPS sum small warehouse: {gps_sum}");
+    }
+    if part.two() {
+        let (mut warehouse, moves) = parse_warehouse(&data, true)?;
+        warehouse.execute_protocol(&moves);
+        let gps_sum = warehouse.compute_gps_sum();
+        println!("GPS sum big warehouse: {gps_sum}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        15
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (mut warehouse, moves) = parse_warehouse(&data, false)?;
+        warehouse.execute_protocol(&moves);
+        Ok(warehouse.compute_gps_sum().to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (mut warehouse, moves) = parse_warehouse(&data, true)?;
+        warehouse.execute_protocol(&moves);
+        Ok(warehouse.compute_gps_sum().to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -315,7 +338,7 @@ mod tests {
 
 <vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let (warehouse, moves) = parse_small_warehouse(input).unwrap();
+        let (warehouse, moves) = parse_warehouse(input, false).unwrap();
         let Pos(width, height) = warehouse.size;
 
         assert_eq!(width, 10);
@@ -343,7 +366,7 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
 
 <vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let (warehouse, moves) = parse_big_warehouse(input).unwrap();
+        let (warehouse, moves) = parse_warehouse(input, true).unwrap();
         let Pos(width, height) = warehouse.size;
 
         assert_eq!(width, 20);
@@ -392,7 +415,7 @@ vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
 <><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
 ^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let (mut warehouse, moves) = parse_small_warehouse(input).unwrap();
+        let (mut warehouse, moves) = parse_warehouse(input, false).unwrap();
         warehouse.execute_protocol(&moves);
         let expected = "##########
 #.O.O.OOO#
@@ -433,7 +456,7 @@ vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
 <><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
 ^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let (mut warehouse, moves) = parse_big_warehouse(input).unwrap();
+        let (mut warehouse, moves) = parse_warehouse(input, true).unwrap();
         warehouse.execute_protocol(&moves);
         let expected = "####################
 ##[].......[].[][]##
@@ -450,4 +473,99 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
         let gps_sum = warehouse.compute_gps_sum();
         assert_eq!(gps_sum, 9021);
     }
+
+    #[test]
+    fn test_example_big_small_input() {
+        let input = "#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^";
+        let (mut warehouse, moves) = parse_warehouse(input, true).unwrap();
+        warehouse.execute_protocol(&moves);
+        let expected = "##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############";
+        let output = format!("{warehouse}");
+        assert_eq!(output, expected);
+        let gps_sum = warehouse.compute_gps_sum();
+        assert_eq!(gps_sum, 105 + 207 + 306);
+    }
+
+    #[test]
+    fn test_step_and_undo() {
+        let input = "#######
+#.@O..#
+#######
+
+>";
+        let (mut warehouse, moves) = parse_warehouse(input, false).unwrap();
+        let before = format!("{warehouse}");
+        assert!(warehouse.step(moves[0]));
+        assert_eq!(format!("{warehouse}"), "#######\n#..@O.#\n#######");
+        assert!(warehouse.step(Direction::Right));
+        assert!(!warehouse.step(Direction::Right));
+        assert_eq!(warehouse.undo(), Some(Direction::Right));
+        assert_eq!(warehouse.undo(), Some(Direction::Right));
+        assert_eq!(warehouse.undo(), Some(Direction::Right));
+        assert_eq!(warehouse.undo(), None);
+        assert_eq!(format!("{warehouse}"), before);
+        assert_eq!(warehouse.robot, Pos(2, 1));
+    }
+
+    #[test]
+    fn test_big_box_diamond() {
+        // two boxes push the same box above them, which must only move once
+        let input = "##########
+##......##
+##..[]..##
+##.[][].##
+##..[]..##
+##..@...##
+##########";
+        let mut robot_at = None;
+        let parser = |x, y, c| match c {
+            '.' => Ok(Tile::Empty),
+            '#' => Ok(Tile::Wall),
+            '[' => Ok(Tile::BigBoxL),
+            ']' => Ok(Tile::BigBoxR),
+            '@' => {
+                robot_at = Some(Pos(x, y));
+                Ok(Tile::Robot)
+            }
+            _ => Err(AocError::ParseError),
+        };
+        let tiles = util::parse_tiles(input, parser).unwrap();
+        let mut warehouse = Warehouse {
+            size: Pos(tiles[0].len(), tiles.len()),
+            robot: robot_at.unwrap(),
+            tiles,
+            history: Vec::new(),
+        };
+        let gps_sum = warehouse.compute_gps_sum();
+
+        assert!(warehouse.step(Direction::Up));
+        let expected = "##########
+##..[]..##
+##.[][].##
+##..[]..##
+##..@...##
+##......##
+##########";
+        assert_eq!(format!("{warehouse}"), expected);
+        assert_eq!(warehouse.robot, Pos(4, 4));
+        assert_eq!(warehouse.compute_gps_sum(), gps_sum - 4 * 100);
+
+        // the top box hits the wall now
+        assert!(!warehouse.step(Direction::Up));
+        assert_eq!(format!("{warehouse}"), expected);
+    }
 }
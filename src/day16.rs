@@ -1,12 +1,13 @@
 use anyhow::Result;
 use std::{
-    collections::{HashSet, VecDeque},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet, VecDeque},
     fmt::Write,
     marker::PhantomData,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 struct Pos(usize, usize);
@@ -122,7 +123,7 @@ struct Maze {
 impl Maze {
     fn parse(input: &str) -> Result<Maze> {
         let mut start = None;
-        let mut end = None;
+        let mut has_end = false;
         let parser = |x, y, c| match c {
             '.' => Ok(Tile::Empty(Visited::default())),
             '#' => Ok(Tile::Wall),
@@ -130,8 +131,8 @@ impl Maze {
                 start = Some(Pos(x, y));
                 Ok(Tile::Start(Visited::default()))
             }
-            'E' if end.is_none() => {
-                end = Some(Pos(x, y));
+            'E' => {
+                has_end = true;
                 Ok(Tile::End(Visited::default()))
             }
             _ => Err(AocError::ParseError),
@@ -139,7 +140,9 @@ impl Maze {
 
         let tiles = util::parse_tiles(input, parser)?;
         let start = start.ok_or(AocError::ParseError)?;
-        let _end = end.ok_or(AocError::ParseError)?;
+        if !has_end {
+            return Err(AocError::ParseError.into());
+        }
 
         let height = tiles.len();
         let width = tiles[0].len();
@@ -178,6 +181,21 @@ struct MoveNode {
     last: Vec<usize>,
 }
 
+#[derive(Copy, Clone, Debug)]
+struct Costs {
+    turn: usize,
+    step: usize,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Costs {
+            turn: 1000,
+            step: 1,
+        }
+    }
+}
+
 struct Init;
 struct Done;
 
@@ -187,15 +205,17 @@ impl DijkstraState for Done {}
 
 struct Dijkstra<T: DijkstraState> {
     maze: Maze,
+    costs: Costs,
     nodes: Vec<MoveNode>,
-    unvisited: Vec<usize>,
+    // min-heap of (points, node index), ties go to the older node
+    unvisited: BinaryHeap<Reverse<(usize, usize)>>,
     best_points: Option<usize>,
     best_paths: Vec<MoveNode>,
     state: PhantomData<T>,
 }
 
 impl Dijkstra<Init> {
-    fn new(maze: Maze) -> Self {
+    fn new(maze: Maze, costs: Costs) -> Self {
         let initial = MoveNode {
             pos: maze.start,
             facing: Direction::East,
@@ -203,9 +223,10 @@ impl Dijkstra<Init> {
             last: Vec::new(),
         };
         let nodes = vec![initial];
-        let unvisited = vec![0];
+        let unvisited = BinaryHeap::from([Reverse((0, 0))]);
         Self {
             maze,
+            costs,
             nodes,
             unvisited,
             best_points: None,
@@ -215,25 +236,15 @@ impl Dijkstra<Init> {
     }
 
     fn get_next_unvisited(&mut self) -> Option<(usize, MoveNode)> {
-        if self.unvisited.is_empty() {
-            return None;
-        }
-
-        let mut smallest_index = None;
-        let mut smallest_points = None;
-        for (ii, i) in self.unvisited.iter().enumerate() {
-            let cur_points = self.nodes[*i].points;
-            if smallest_points.is_none_or(|p| p > cur_points) {
-                smallest_points = Some(cur_points);
-                smallest_index = Some(ii);
-            }
-        }
+        let Reverse((_, index)) = self.unvisited.pop()?;
+        let node = self.nodes[index].clone();
+        Some((index, node))
+    }
 
-        smallest_index.map(|ii| {
-            let index = self.unvisited.remove(ii);
-            let node = self.nodes[index].clone();
-            (index, node)
-        })
+    fn push_node(&mut self, node: MoveNode) {
+        let new_index = self.nodes.len();
+        self.unvisited.push(Reverse((node.points, new_index)));
+        self.nodes.push(node);
     }
 
     fn solve(mut self) -> Dijkstra<Done> {
@@ -266,10 +277,12 @@ impl Dijkstra<Init> {
             // have not been here yet, so prepare another step
             self.maze.at_mut(pos).visit(facing, cur_index);
 
+            // the first end reached is the cheapest, further ends only count at equal points
             if let Tile::End(_) = current_tile {
-                assert!(self.best_points.is_none_or(|p| p == points));
-                self.best_points = Some(points);
-                self.best_paths.push(node);
+                if self.best_points.is_none_or(|p| p == points) {
+                    self.best_points = Some(points);
+                    self.best_paths.push(node);
+                }
                 continue;
             }
 
@@ -282,29 +295,23 @@ impl Dijkstra<Init> {
             // add new node for turning left
             let facing_left = facing.turn_left();
             if left != Tile::Wall {
-                let new_index = self.nodes.len();
-                let new_node = MoveNode {
+                self.push_node(MoveNode {
                     pos,
                     facing: facing_left,
-                    points: points + 1000,
+                    points: points + self.costs.turn,
                     last: vec![cur_index],
-                };
-                self.unvisited.push(new_index);
-                self.nodes.push(new_node);
+                });
             }
 
             // add new node for turning right
             let facing_right = facing.turn_right();
             if right != Tile::Wall {
-                let new_index = self.nodes.len();
-                let new_node = MoveNode {
+                self.push_node(MoveNode {
                     pos,
                     facing: facing_right,
-                    points: points + 1000,
+                    points: points + self.costs.turn,
                     last: vec![cur_index],
-                };
-                self.unvisited.push(new_index);
-                self.nodes.push(new_node);
+                });
             }
 
             // add new node for moving forward
@@ -313,20 +320,18 @@ impl Dijkstra<Init> {
             };
             let tile_ahead = self.maze.at(new_pos);
             if tile_ahead != Tile::Wall {
-                let new_index = self.nodes.len();
-                let new_node = MoveNode {
+                self.push_node(MoveNode {
                     pos: new_pos,
                     facing,
-                    points: points + 1,
+                    points: points + self.costs.step,
                     last: vec![cur_index],
-                };
-                self.unvisited.push(new_index);
-                self.nodes.push(new_node);
+                });
             }
         }
 
         let Dijkstra {
             maze,
+            costs,
             nodes,
             unvisited,
             best_points,
@@ -335,6 +340,7 @@ impl Dijkstra<Init> {
         } = self;
         Dijkstra {
             maze,
+            costs,
             nodes,
             unvisited,
             best_points,
@@ -352,6 +358,27 @@ impl Dijkstra<Done> {
     fn best_paths(&self) -> (&Vec<MoveNode>, &Vec<MoveNode>) {
         (&self.best_paths, &self.nodes)
     }
+
+    // cells of one optimal path from start to end, empty if there is none
+    #[allow(dead_code)]
+    fn best_path_coords(&self) -> Vec<Pos> {
+        let mut path = Vec::new();
+        let mut node = self.best_paths.first();
+        let mut index = usize::MAX;
+        while let Some(MoveNode { pos, last, .. }) = node {
+            // turning in place yields the same cell twice
+            if path.last() != Some(pos) {
+                path.push(*pos);
+            }
+            // every parent lies on an optimal path, only following parents that
+            // were created earlier rules out cycles from free turns
+            let parent = last.iter().copied().filter(|&i| i < index).min();
+            node = parent.map(|i| &self.nodes[i]);
+            index = parent.unwrap_or(0);
+        }
+        path.reverse();
+        path
+    }
 }
 
 fn count_unique_pos_and_dir(
@@ -388,24 +415,92 @@ fn count_seats(final_nodes: &[MoveNode], nodes: &[MoveNode]) -> usize {
     set.len()
 }
 
-pub fn run() -> Result<()> {
+#[cfg(test)]
+fn find_best_points_astar(maze: &Maze) -> Option<usize> {
+    let end = maze.tiles.iter().enumerate().find_map(|(y, row)| {
+        row.iter()
+            .position(|t| matches!(t, Tile::End(_)))
+            .map(|x| Pos(x, y))
+    })?;
+
+    // facing None marks having arrived at the end, regardless of direction
+    let start = (maze.start, Some(Direction::East));
+    let goal = (end, None);
+    let not_wall = |pos: Pos, dir: Direction| {
+        maze.neighbor_pos(pos, dir)
+            .filter(|&p| maze.at(p) != Tile::Wall)
+    };
+    let neighbors = |(pos, facing): (Pos, Option<Direction>)| {
+        let mut next = Vec::new();
+        let Some(facing) = facing else {
+            return next;
+        };
+        if pos == end {
+            next.push(((pos, None), 0));
+            return next;
+        }
+        for turned in [facing.turn_left(), facing.turn_right()] {
+            if not_wall(pos, turned).is_some() {
+                next.push(((pos, Some(turned)), 1000));
+            }
+        }
+        if let Some(ahead) = not_wall(pos, facing) {
+            next.push(((ahead, Some(facing)), 1));
+        }
+        next
+    };
+    let heuristic =
+        |(Pos(x, y), _): (Pos, Option<Direction>)| x.abs_diff(end.0) + y.abs_diff(end.1);
+
+    util::astar(start, goal, neighbors, heuristic).map(|(_, points)| points)
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 16");
     let path = PathBuf::from("./resources/day16.txt");
     let data = util::get_data_string(&path)?;
     let maze = Maze::parse(&data)?;
-    let dijkstra = Dijkstra::new(maze);
+    let dijkstra = Dijkstra::new(maze, Costs::default());
     let dijkstra = dijkstra.solve();
-    let best_points = dijkstra
-        .best_points()
-        .ok_or(AocError::ValueError("could not solve maze".into()))?;
-    println!("least points to solve maze: {best_points}");
-
-    let (final_nodes, all_nodes) = dijkstra.best_paths();
-    let count = count_seats(final_nodes, all_nodes);
-    println!("number of seats: {count}");
+    if part.one() {
+        let best_points = dijkstra
+            .best_points()
+            .ok_or(AocError::ValueError("could not solve maze".into()))?;
+        println!("least points to solve maze: {best_points}");
+    }
+
+    if part.two() {
+        let (final_nodes, all_nodes) = dijkstra.best_paths();
+        let count = count_seats(final_nodes, all_nodes);
+        println!("number of seats: {count}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        16
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let dijkstra = Dijkstra::new(Maze::parse(&data)?, Costs::default()).solve();
+        let best_points = dijkstra
+            .best_points()
+            .ok_or(AocError::ValueError("could not solve maze".into()))?;
+        Ok(best_points.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let dijkstra = Dijkstra::new(Maze::parse(&data)?, Costs::default()).solve();
+        let (final_nodes, all_nodes) = dijkstra.best_paths();
+        Ok(count_seats(final_nodes, all_nodes).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -428,7 +523,7 @@ mod tests {
 #S..#.....#...#
 ###############";
         let maze = Maze::parse(input).unwrap();
-        let dijkstra = Dijkstra::new(maze);
+        let dijkstra = Dijkstra::new(maze, Costs::default());
         let dijkstra = dijkstra.solve();
         let best_points = dijkstra.best_points().unwrap();
         assert_eq!(best_points, 7036);
@@ -452,7 +547,7 @@ mod tests {
 #S..#.....#...#
 ###############";
         let maze = Maze::parse(input).unwrap();
-        let dijkstra = Dijkstra::new(maze);
+        let dijkstra = Dijkstra::new(maze, Costs::default());
         let dijkstra = dijkstra.solve();
         let (final_nodes, all_nodes) = dijkstra.best_paths();
         let set = count_unique_pos_and_dir(final_nodes, all_nodes);
@@ -466,6 +561,82 @@ mod tests {
         assert!(set.contains(&(Pos(6, 7), Direction::East)));
     }
 
+    #[test]
+    fn test_best_path_coords() {
+        let input = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+        let maze = Maze::parse(input).unwrap();
+        let dijkstra = Dijkstra::new(maze, Costs::default()).solve();
+        let path = dijkstra.best_path_coords();
+        assert_eq!(path.first(), Some(&Pos(1, 13)));
+        assert_eq!(path.last(), Some(&Pos(13, 1)));
+        // 7036 points are 7 turns and 36 steps
+        assert_eq!(path.len(), 37);
+        for pair in path.windows(2) {
+            let [Pos(x1, y1), Pos(x2, y2)] = [pair[0], pair[1]];
+            assert_eq!(x1.abs_diff(x2) + y1.abs_diff(y2), 1);
+        }
+    }
+
+    #[test]
+    fn test_free_turns() {
+        let input = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+        let maze = Maze::parse(input).unwrap();
+        let costs = Costs { turn: 0, step: 1 };
+        let dijkstra = Dijkstra::new(maze, costs).solve();
+        assert_eq!(dijkstra.best_points(), Some(28));
+        assert_eq!(dijkstra.best_path_coords().len(), 29);
+    }
+
+    #[test]
+    fn test_multiple_ends() {
+        let input = "#######
+#E....#
+#.S..E#
+#######";
+        let maze = Maze::parse(input).unwrap();
+        let dijkstra = Dijkstra::new(maze, Costs::default()).solve();
+        assert_eq!(dijkstra.best_points(), Some(3));
+        assert_eq!(dijkstra.best_path_coords().last(), Some(&Pos(5, 2)));
+        let (final_nodes, all_nodes) = dijkstra.best_paths();
+        assert_eq!(count_seats(final_nodes, all_nodes), 4);
+
+        let input = "#######
+#E....#
+#.S.#E#
+#######";
+        let maze = Maze::parse(input).unwrap();
+        let dijkstra = Dijkstra::new(maze, Costs::default()).solve();
+        assert_eq!(dijkstra.best_points(), Some(2002));
+    }
+
     #[test]
     fn test_maze_seats() {
         let input = "###############
@@ -484,11 +655,35 @@ mod tests {
 #S..#.....#...#
 ###############";
         let maze = Maze::parse(input).unwrap();
-        let dijkstra = Dijkstra::new(maze);
+        let dijkstra = Dijkstra::new(maze, Costs::default());
         let dijkstra = dijkstra.solve();
         let (final_nodes, all_nodes) = dijkstra.best_paths();
         let count = count_seats(final_nodes, all_nodes);
 
         assert_eq!(count, 45);
     }
+
+    #[test]
+    fn test_astar_same_as_dijkstra() {
+        let input = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+        let maze = Maze::parse(input).unwrap();
+        let astar_points = find_best_points_astar(&maze);
+        let dijkstra = Dijkstra::new(maze, Costs::default()).solve();
+        assert_eq!(astar_points, dijkstra.best_points());
+        assert_eq!(astar_points, Some(7036));
+    }
 }
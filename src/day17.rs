@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Clone, Default, Debug)]
 struct Registers {
@@ -92,6 +95,29 @@ impl Instruction {
         matches!(self, Instruction::Halt)
     }
 
+    // mnemonic and operand with combo operands resolved to registers
+    fn disassemble(self) -> String {
+        use Instruction::*;
+        let combo = |op: u8| match op {
+            v @ 0..=3 => v.to_string(),
+            4 => "A".to_string(),
+            5 => "B".to_string(),
+            6 => "C".to_string(),
+            _ => "RESERVED".to_string(),
+        };
+        match self {
+            Adv(op) => format!("adv {}", combo(op)),
+            Bxl(op) => format!("bxl {op}"),
+            Bst(op) => format!("bst {}", combo(op)),
+            Jnz(op) => format!("jnz {op}"),
+            Bxc(_op) => "bxc".to_string(),
+            Out(op) => format!("out {}", combo(op)),
+            Bdv(op) => format!("bdv {}", combo(op)),
+            Cdv(op) => format!("cdv {}", combo(op)),
+            Halt => "halt".to_string(),
+        }
+    }
+
     fn as_opcode(self) -> [u8; 2] {
         use Instruction::*;
         match self {
@@ -108,6 +134,8 @@ impl Instruction {
     }
 }
 
+const INSTRUCTION_BUDGET: usize = 1_000_000;
+
 #[derive(Clone, Default)]
 struct Computer {
     registers: Registers,
@@ -137,19 +165,19 @@ impl Computer {
     fn parse(input: &str) -> Result<Computer> {
         use AocError::ParseError;
 
-        let (reg_a, input) = input.split_once("\n").ok_or(ParseError)?;
-        let (reg_b, input) = input.split_once("\n").ok_or(ParseError)?;
-        let (reg_c, input) = input.split_once("\n").ok_or(ParseError)?;
-        let (empty, input) = input.split_once("\n").ok_or(ParseError)?;
+        // registers and program are separated by a blank line, so empty lines are kept here
+        let mut lines = input.split("\n").map(|l| l.strip_suffix("\r").unwrap_or(l));
+        let reg_a = lines.next().ok_or(ParseError)?;
+        let reg_b = lines.next().ok_or(ParseError)?;
+        let reg_c = lines.next().ok_or(ParseError)?;
+        let empty = lines.next().ok_or(ParseError)?;
         if !empty.is_empty() {
             return Err(ParseError.into());
         }
-
-        let program = match input.split_once("\n") {
-            Some((_, input)) if !input.is_empty() => return Err(ParseError.into()),
-            Some((program, _)) => program,
-            None => input,
-        };
+        let program = lines.next().ok_or(ParseError)?;
+        if lines.any(|l| !l.is_empty()) {
+            return Err(ParseError.into());
+        }
 
         let reg_a = reg_a.strip_prefix("Register A: ").ok_or(ParseError)?;
         let reg_a = reg_a.parse()?;
@@ -202,6 +230,17 @@ impl Computer {
         })
     }
 
+    // one instruction per line, prefixed with its instruction pointer
+    #[allow(dead_code)]
+    fn disassemble(&self) -> String {
+        self.program
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| format!("{}: {}", index * 2, instruction.disassemble()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn next_instruction(&mut self) -> Instruction {
         let index = self.instruction_pointer / 2;
         self.instruction_pointer += 2;
@@ -223,7 +262,8 @@ impl Computer {
         }
     }
 
-    fn do_instruction(&mut self, instruction: Instruction) {
+    // returns the value written by Out
+    fn do_instruction(&mut self, instruction: Instruction) -> Option<u8> {
         use Instruction::*;
         match instruction {
             Adv(op) => {
@@ -252,7 +292,7 @@ impl Computer {
             }
             Out(op) => {
                 let val = self.get_combo_value(op);
-                self.output.push((val % 8) as u8);
+                return Some((val % 8) as u8);
             }
             Bdv(op) => {
                 let val = self.get_combo_value(op);
@@ -268,6 +308,7 @@ impl Computer {
             }
             Halt => (),
         }
+        None
     }
 
     fn reset(&mut self, registers: Registers) {
@@ -276,17 +317,32 @@ impl Computer {
         self.registers = registers;
     }
 
-    fn run(&mut self) -> &Vec<u8> {
-        let mut next = self.next_instruction();
+    // executes lazily, only as far as needed for the next output,
+    // errors once and then stops when more than `budget` instructions were executed
+    fn outputs(&mut self, budget: usize) -> impl Iterator<Item = Result<u8, AocError>> + '_ {
         let mut count = 0;
-        while !next.halt() {
-            assert!(count < 1_000_000);
+        std::iter::from_fn(move || loop {
+            if count > budget {
+                return None;
+            }
+            let next = self.next_instruction();
+            if next.halt() {
+                return None;
+            }
             count += 1;
-            // println!("count: {count}, next: {next}\nstate: {:?}", self.registers);
-            self.do_instruction(next);
-            next = self.next_instruction();
-        }
-        &self.output
+            if count > budget {
+                return Some(Err(AocError::ExecutionLimit(budget)));
+            }
+            if let Some(out) = self.do_instruction(next) {
+                return Some(Ok(out));
+            }
+        })
+    }
+
+    fn run(&mut self, budget: usize) -> Result<&Vec<u8>, AocError> {
+        let output = self.outputs(budget).collect::<Result<Vec<_>, _>>()?;
+        self.output.extend(output);
+        Ok(&self.output)
     }
 }
 
@@ -307,7 +363,27 @@ struct DFSNode {
     reg: usize,
 }
 
-fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> usize {
+// the search assumes a single loop that outputs once, shifts A by 3 bits and
+// jumps back to the start at the end, so every output depends on one more octal digit
+fn check_octal_loop(program: &[Instruction]) -> Result<(), AocError> {
+    use Instruction::*;
+
+    let count = |f: fn(&Instruction) -> bool| program.iter().filter(|i| f(i)).count();
+    let outs = count(|i| matches!(i, Out(_)));
+    let shifts = count(|i| matches!(i, Adv(3)));
+    let other_adv = count(|i| matches!(i, Adv(op) if *op != 3));
+    let jumps = count(|i| matches!(i, Jnz(_)));
+    let loops_at_end = matches!(program.last(), Some(Jnz(0)));
+    if outs != 1 || shifts != 1 || other_adv != 0 || jumps != 1 || !loops_at_end {
+        let s = "program does not output one value per 3 bits of register A".to_string();
+        return Err(AocError::ValueError(s));
+    }
+    Ok(())
+}
+
+fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> Result<usize> {
+    check_octal_loop(&computer.program)?;
+
     let initial = DFSNode {
         step: 0,
         next: 0,
@@ -320,7 +396,7 @@ fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> usize
 
         if step == expected.len() {
             // found reg
-            return reg;
+            return Ok(reg);
         }
 
         assert!(stack.len() < expected.len());
@@ -334,7 +410,8 @@ fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> usize
         };
         computer.reset(registers);
 
-        let next_produced = computer.run().first();
+        // running out of budget counts as wrong output
+        let next_produced = computer.outputs(INSTRUCTION_BUDGET).next();
 
         let can_try_next_val = next < 7;
         if can_try_next_val {
@@ -348,7 +425,7 @@ fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> usize
         }
 
         let next_expected = expected[expected.len() - 1 - step];
-        let correct_output = next_produced.is_some_and(|&out| out == next_expected);
+        let correct_output = next_produced.is_some_and(|out| out.is_ok_and(|v| v == next_expected));
         if correct_output {
             // go to next step
             let for_next_step = DFSNode {
@@ -360,28 +437,59 @@ fn find_needed_register_value(mut computer: Computer, expected: &[u8]) -> usize
         }
     }
 
-    panic!("nothing works");
+    let s = "no register value reproduces the program".to_string();
+    Err(AocError::ValueError(s).into())
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 17");
     let path = PathBuf::from("./resources/day17.txt");
     let data = util::get_data_string(&path)?;
     let mut computer = Computer::parse(&data).unwrap();
-    let values = computer.run();
-    let output = accumulate_string(values);
-    println!("computer outputs: {output}");
-
-    let expected_output = computer
-        .program
-        .iter()
-        .flat_map(|i| i.as_opcode())
-        .collect::<Vec<u8>>();
-    let needed_reg = find_needed_register_value(computer, &expected_output);
-    println!("to get identity program use: {needed_reg}");
+    if part.one() {
+        let values = computer.run(INSTRUCTION_BUDGET)?;
+        let output = accumulate_string(values);
+        println!("computer outputs: {output}");
+    }
+
+    if part.two() {
+        let expected_output = computer
+            .program
+            .iter()
+            .flat_map(|i| i.as_opcode())
+            .collect::<Vec<u8>>();
+        let needed_reg = find_needed_register_value(computer, &expected_output)?;
+        println!("to get identity program use: {needed_reg}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        17
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let mut computer = Computer::parse(&data)?;
+        let values = computer.run(INSTRUCTION_BUDGET)?;
+        Ok(accumulate_string(values))
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let computer = Computer::parse(&data)?;
+        let expected_output = computer
+            .program
+            .iter()
+            .flat_map(|i| i.as_opcode())
+            .collect::<Vec<u8>>();
+        Ok(find_needed_register_value(computer, &expected_output)?.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -394,11 +502,69 @@ Register C: 0
 
 Program: 0,1,5,4,3,0";
         let mut computer = Computer::parse(input).unwrap();
-        let values = computer.run();
+        let values = computer.run(INSTRUCTION_BUDGET).unwrap();
+        let output = accumulate_string(values);
+        assert_eq!(output, "4,6,3,5,6,3,5,2,1,0");
+    }
+
+    #[test]
+    fn test_example_computer_crlf() {
+        let input =
+            "Register A: 729\r\nRegister B: 0\r\nRegister C: 0\r\n\r\nProgram: 0,1,5,4,3,0\r\n";
+        let mut computer = Computer::parse(input).unwrap();
+        let values = computer.run(INSTRUCTION_BUDGET).unwrap();
         let output = accumulate_string(values);
         assert_eq!(output, "4,6,3,5,6,3,5,2,1,0");
     }
 
+    #[test]
+    fn test_lazy_outputs() {
+        let input = "Register A: 729
+Register B: 0
+Register C: 0
+
+Program: 0,1,5,4,3,0";
+        let mut computer = Computer::parse(input).unwrap();
+        let first = computer.outputs(100).take(3).collect::<Result<Vec<_>, _>>();
+        assert_eq!(first.unwrap(), vec![4, 6, 3]);
+        // continues where the last output left off
+        assert!(matches!(computer.outputs(100).next(), Some(Ok(5))));
+        assert!(computer.output.is_empty());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let input = "Register A: 729
+Register B: 0
+Register C: 0
+
+Program: 0,1,5,4,3,0";
+        let computer = Computer::parse(input).unwrap();
+        assert_eq!(computer.disassemble(), "0: adv 1\n2: out A\n4: jnz 0");
+    }
+
+    #[test]
+    fn test_execution_limit() {
+        let input = "Register A: 1
+Register B: 0
+Register C: 0
+
+Program: 3,0";
+        let mut computer = Computer::parse(input).unwrap();
+        let result = computer.run(1000);
+        assert!(matches!(result, Err(AocError::ExecutionLimit(1000))));
+    }
+
+    #[test]
+    fn test_parse_requires_blank_line() {
+        let input = "Register A: 729\nRegister B: 0\nRegister C: 0\nProgram: 0,1,5,4,3,0";
+        assert!(Computer::parse(input).is_err());
+        let input = "Register A: 729\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0\n\nfoo";
+        assert!(Computer::parse(input).is_err());
+        let input = "Register A: 729\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0\n";
+        assert!(Computer::parse(input).is_ok());
+    }
+
     #[test]
     fn test_find_register_value() {
         let input = "Register A: 2024
@@ -412,7 +578,23 @@ Program: 0,3,5,4,3,0";
             .iter()
             .flat_map(|i| i.as_opcode())
             .collect::<Vec<u8>>();
-        let reg = find_needed_register_value(computer, &expected_output);
+        let reg = find_needed_register_value(computer, &expected_output).unwrap();
         assert_eq!(reg, 117440);
     }
+
+    #[test]
+    fn test_find_register_value_rejects_two_outputs() {
+        let input = "Register A: 2024
+Register B: 0
+Register C: 0
+
+Program: 0,3,5,4,5,4,3,0";
+        let computer = Computer::parse(input).unwrap();
+        let expected_output = computer
+            .program
+            .iter()
+            .flat_map(|i| i.as_opcode())
+            .collect::<Vec<u8>>();
+        assert!(find_needed_register_value(computer, &expected_output).is_err());
+    }
 }
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{collections::VecDeque, path::PathBuf};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Pos(usize, usize);
@@ -20,20 +23,35 @@ impl Pos {
 enum Byte {
     #[default]
     Empty,
-    Visited,
+    // visited during the search of the stored generation, stale ones count as empty
+    Visited(u32),
     Corrupt,
 }
 
+#[derive(Clone)]
 struct Memory {
     size: Pos,
     bytes: Vec<Byte>,
+    generation: u32,
+    // where each byte was first reached from, only valid for bytes visited in this generation
+    parents: Vec<Option<Pos>>,
 }
 
 impl Memory {
     fn new(size: usize) -> Memory {
-        let bytes = vec![Byte::Empty; size * size];
-        let size = Pos(size, size);
-        Self { size, bytes }
+        Self::with_dimensions(size, size)
+    }
+
+    fn with_dimensions(width: usize, height: usize) -> Memory {
+        let bytes = vec![Byte::Empty; width * height];
+        let parents = vec![None; width * height];
+        let size = Pos(width, height);
+        Self {
+            size,
+            bytes,
+            generation: 0,
+            parents,
+        }
     }
 
     fn at(&self, x: usize, y: usize) -> Byte {
@@ -52,53 +70,75 @@ impl Memory {
         }
     }
 
-    fn reset_visited(&mut self) {
-        for b in self.bytes.iter_mut() {
-            if let Byte::Visited = *b {
-                *b = Byte::Empty;
-            }
-        }
+    // top left to bottom right, the endpoints of the puzzle
+    fn corners(&self) -> (Pos, Pos) {
+        let Pos(width, height) = self.size;
+        (
+            Pos(0, 0),
+            Pos(width.saturating_sub(1), height.saturating_sub(1)),
+        )
+    }
+
+    fn find_exit(&mut self, start: Pos, goal: Pos) -> Option<usize> {
+        self.find_exit_path(start, goal).map(|path| path.len() - 1)
     }
 
-    fn find_exit(&mut self) -> Option<usize> {
+    fn find_exit_path(&mut self, start: Pos, goal: Pos) -> Option<Vec<Pos>> {
         struct BFSNode {
             at: Pos,
             step: usize,
+            from: Option<Pos>,
         }
 
-        self.reset_visited();
+        // forget all earlier searches without touching the bytes
+        self.generation += 1;
+        let generation = self.generation;
         let Pos(width, height) = self.size;
-        if width == 0 || height == 0 {
+        let within = |Pos(x, y): Pos| x < width && y < height;
+        if !within(start) || !within(goal) {
             return None;
         }
-        let goal = Pos(width - 1, height - 1);
         let initial = BFSNode {
-            at: Pos(0, 0),
+            at: start,
             step: 0,
+            from: None,
         };
         let mut queue = VecDeque::new();
         queue.push_back(initial);
         while let Some(node) = queue.pop_front() {
             assert!(queue.len() < width * height);
-            let BFSNode { at, step } = node;
+            let BFSNode { at, step, from } = node;
             assert!(step <= width * height);
             let Pos(x, y) = at;
 
-            if let Byte::Visited | Byte::Corrupt = self.at(x, y) {
-                continue;
+            match self.at(x, y) {
+                Byte::Visited(g) if g == generation => continue,
+                Byte::Corrupt => continue,
+                _ => (),
             }
 
+            *self.at_mut(x, y) = Byte::Visited(generation);
+            self.parents[y * width + x] = from;
+
             if at == goal {
-                return Some(step);
+                let mut path = vec![at];
+                while let Some(Pos(x, y)) = path.last().copied() {
+                    match self.parents[y * width + x] {
+                        Some(parent) => path.push(parent),
+                        None => break,
+                    }
+                }
+                path.reverse();
+                return Some(path);
             }
 
-            *self.at_mut(x, y) = Byte::Visited;
-
             let step = step + 1;
+            let from = Some(at);
             if y > 0 {
                 let next = BFSNode {
                     at: Pos(x, y - 1),
                     step,
+                    from,
                 };
                 queue.push_back(next);
             }
@@ -106,6 +146,7 @@ impl Memory {
                 let next = BFSNode {
                     at: Pos(x + 1, y),
                     step,
+                    from,
                 };
                 queue.push_back(next);
             }
@@ -113,6 +154,7 @@ impl Memory {
                 let next = BFSNode {
                     at: Pos(x, y + 1),
                     step,
+                    from,
                 };
                 queue.push_back(next);
             }
@@ -120,6 +162,7 @@ impl Memory {
                 let next = BFSNode {
                     at: Pos(x - 1, y),
                     step,
+                    from,
                 };
                 queue.push_back(next);
             }
@@ -127,14 +170,34 @@ impl Memory {
         None
     }
 
+    // corrupt the bytes in place for the search and put the old ones back afterwards
+    fn blocked_after(&mut self, corruption: &[Pos]) -> bool {
+        let previous: Vec<_> = corruption.iter().map(|&Pos(x, y)| self.at(x, y)).collect();
+        self.add_corruption(corruption);
+        let (start, goal) = self.corners();
+        let blocked = self.find_exit(start, goal).is_none();
+        for (&Pos(x, y), &byte) in corruption.iter().zip(&previous).rev() {
+            *self.at_mut(x, y) = byte;
+        }
+        blocked
+    }
+
+    // once the exit is blocked it stays blocked, so binary search the shortest blocking prefix
     fn find_cutoff(&mut self, corruption: &[Pos]) -> Option<Pos> {
-        for &pos in corruption {
-            self.add_corruption(&[pos]);
-            if self.find_exit().is_none() {
-                return Some(pos);
+        if !self.blocked_after(corruption) {
+            return None;
+        }
+        let mut open = 0;
+        let mut blocked = corruption.len();
+        while blocked - open > 1 {
+            let mid = open + (blocked - open) / 2;
+            if self.blocked_after(&corruption[..mid]) {
+                blocked = mid;
+            } else {
+                open = mid;
             }
         }
-        None
+        Some(corruption[blocked - 1])
     }
 }
 
@@ -146,7 +209,7 @@ fn parse_corruption(input: &str) -> Result<Vec<Pos>> {
         .collect()
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 18");
     let path = PathBuf::from("./resources/day18.txt");
     let data = util::get_data_string(&path)?;
@@ -154,18 +217,54 @@ pub fn run() -> Result<()> {
 
     let mut memory = Memory::new(71);
     memory.add_corruption(&corruption[..1024]);
-    let steps = memory
-        .find_exit()
-        .ok_or(AocError::ValueError("never exit".into()))?;
-    println!("Get to exit after {steps} steps");
-
-    let cutoff = memory
-        .find_cutoff(&corruption[1024..])
-        .ok_or(AocError::ValueError("never cut off".into()))?;
-    println!("You will get cut off by byte {cutoff:?}");
+    if part.one() {
+        let (start, goal) = memory.corners();
+        let steps = memory
+            .find_exit(start, goal)
+            .ok_or(AocError::ValueError("never exit".into()))?;
+        println!("Get to exit after {steps} steps");
+    }
+
+    if part.two() {
+        let cutoff = memory
+            .find_cutoff(&corruption[1024..])
+            .ok_or(AocError::ValueError("never cut off".into()))?;
+        println!("You will get cut off by byte {cutoff:?}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        18
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let corruption = parse_corruption(&data)?;
+        let mut memory = Memory::new(71);
+        memory.add_corruption(&corruption[..1024]);
+        let (start, goal) = memory.corners();
+        let steps = memory
+            .find_exit(start, goal)
+            .ok_or(AocError::ValueError("never exit".into()))?;
+        Ok(steps.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let corruption = parse_corruption(&data)?;
+        let mut memory = Memory::new(71);
+        memory.add_corruption(&corruption[..1024]);
+        let Pos(x, y) = memory
+            .find_cutoff(&corruption[1024..])
+            .ok_or(AocError::ValueError("never cut off".into()))?;
+        Ok(format!("{x},{y}"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,8 +299,18 @@ mod tests {
         let corruption = parse_corruption(input).unwrap();
         let mut memory = Memory::new(7);
         memory.add_corruption(&corruption[..12]);
-        let steps = memory.find_exit().unwrap();
+        let (start, goal) = memory.corners();
+        let steps = memory.find_exit(start, goal).unwrap();
         assert_eq!(steps, 22);
+
+        let path = memory.find_exit_path(start, goal).unwrap();
+        assert_eq!(path.len(), steps + 1);
+        assert_eq!(path.first(), Some(&Pos(0, 0)));
+        assert_eq!(path.last(), Some(&Pos(6, 6)));
+        for pair in path.windows(2) {
+            let [Pos(x1, y1), Pos(x2, y2)] = [pair[0], pair[1]];
+            assert_eq!(x1.abs_diff(x2) + y1.abs_diff(y2), 1);
+        }
     }
 
     #[test]
@@ -236,5 +345,25 @@ mod tests {
         memory.add_corruption(&corruption[..12]);
         let cutoff = memory.find_cutoff(&corruption[12..]).unwrap();
         assert_eq!(cutoff, Pos(6, 1));
+        // the probes leave the memory as it was
+        let (start, goal) = memory.corners();
+        assert_eq!(memory.find_exit(start, goal), Some(22));
+        assert_eq!(memory.find_cutoff(&corruption[12..]), Some(Pos(6, 1)));
+    }
+
+    #[test]
+    fn test_rectangular_memory() {
+        let mut memory = Memory::with_dimensions(7, 5);
+        memory.add_corruption(&[Pos(3, 0), Pos(3, 1), Pos(3, 2), Pos(3, 3)]);
+        let (start, goal) = memory.corners();
+        assert_eq!(goal, Pos(6, 4));
+        assert_eq!(memory.find_exit(start, goal), Some(10));
+        assert_eq!(memory.find_exit(goal, start), Some(10));
+        assert_eq!(memory.find_exit(Pos(2, 0), Pos(4, 0)), Some(10));
+        assert_eq!(memory.find_exit(start, Pos(3, 0)), None);
+        assert_eq!(memory.find_exit(start, Pos(7, 0)), None);
+
+        memory.add_corruption(&[Pos(3, 4)]);
+        assert_eq!(memory.find_exit(start, goal), None);
     }
 }
@@ -1,27 +1,18 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
+// a stripe color, any lowercase ascii letter (the puzzle uses w, u, b, r and g)
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Stripe {
-    White,
-    Blue,
-    Black,
-    Red,
-    Green,
-}
+struct Stripe(u8);
 
 impl Stripe {
     fn parse(c: char) -> Result<Stripe> {
-        use Stripe::*;
-        match c {
-            'w' => Ok(White),
-            'u' => Ok(Blue),
-            'b' => Ok(Black),
-            'r' => Ok(Red),
-            'g' => Ok(Green),
-            _ => Err(AocError::ParseError.into()),
+        if c.is_ascii_lowercase() {
+            Ok(Stripe(c as u8))
+        } else {
+            Err(AocError::ParseError.into())
         }
     }
 }
@@ -36,25 +27,101 @@ struct Pattern {
     stripes: Vec<Stripe>,
 }
 
+#[derive(Clone, Debug, Default)]
+struct TrieNode {
+    children: Vec<(Stripe, usize)>,
+    // index of the towel ending here
+    towel: Option<usize>,
+}
+
+// prefix tree of all towels, so matching at a position only walks as deep as the longest towel
+#[derive(Clone, Debug)]
+struct TowelTrie {
+    nodes: Vec<TrieNode>,
+}
+
+impl TowelTrie {
+    fn new(towels: &[Towel]) -> TowelTrie {
+        let mut nodes = vec![TrieNode::default()];
+        for (index, towel) in towels.iter().enumerate() {
+            let mut current = 0;
+            for &stripe in towel.stripes.iter() {
+                current = match nodes[current].children.iter().find(|(s, _)| *s == stripe) {
+                    Some(&(_, child)) => child,
+                    None => {
+                        let child = nodes.len();
+                        nodes.push(TrieNode::default());
+                        nodes[current].children.push((stripe, child));
+                        child
+                    }
+                };
+            }
+            nodes[current].towel.get_or_insert(index);
+        }
+        TowelTrie { nodes }
+    }
+
+    // lengths and indices of all towels that are a prefix of `stripes`
+    fn prefixes<'a>(&'a self, stripes: &'a [Stripe]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let mut current = Some(0);
+        stripes
+            .iter()
+            .map_while(move |stripe| {
+                let node = &self.nodes[current?];
+                current = node
+                    .children
+                    .iter()
+                    .find(|(s, _)| s == stripe)
+                    .map(|&(_, child)| child);
+                current
+            })
+            .enumerate()
+            .filter_map(|(depth, node)| self.nodes[node].towel.map(|towel| (depth + 1, towel)))
+    }
+}
+
 impl Pattern {
-    fn can_combine(&self, towels: &[Towel]) -> usize {
+    fn can_combine(&self, trie: &TowelTrie) -> usize {
         let n_stripes = self.stripes.len();
         let mut table = vec![0; n_stripes + 1];
         table[n_stripes] = 1;
         for split in (0..n_stripes).rev() {
             let (_, to_solve) = self.stripes.split_at(split);
-            for towel in towels {
-                let prefix = towel.stripes.as_slice();
-                let Some(rest) = to_solve.strip_prefix(prefix) else {
-                    continue;
-                };
-                let rest = rest.len();
-                let table_index = n_stripes - rest;
-                table[split] += table[table_index];
+            for (len, _) in trie.prefixes(to_solve) {
+                table[split] += table[split + len];
             }
         }
         table[0]
     }
+
+    // towel indices that concatenate to this pattern, taking the shortest usable towel at each step
+    #[allow(dead_code)]
+    fn decompose(&self, towels: &[Towel]) -> Option<Vec<usize>> {
+        let trie = TowelTrie::new(towels);
+        let n_stripes = self.stripes.len();
+        // whether the remaining stripes from each split can still be covered
+        let mut solvable = vec![false; n_stripes + 1];
+        solvable[n_stripes] = true;
+        for split in (0..n_stripes).rev() {
+            solvable[split] = trie
+                .prefixes(&self.stripes[split..])
+                .any(|(len, _)| solvable[split + len]);
+        }
+        if !solvable[0] {
+            return None;
+        }
+
+        let mut split = 0;
+        let mut indices = Vec::new();
+        while split < n_stripes {
+            let (len, towel) = trie
+                .prefixes(&self.stripes[split..])
+                .find(|&(len, _)| solvable[split + len])?;
+            indices.push(towel);
+            split += len;
+        }
+        Some(indices)
+    }
 }
 
 fn parse_stripes(input: &str) -> Result<(Vec<Towel>, Vec<Pattern>)> {
@@ -83,23 +150,83 @@ fn parse_stripes(input: &str) -> Result<(Vec<Towel>, Vec<Pattern>)> {
 }
 
 fn count_options(patterns: &[Pattern], towels: &[Towel]) -> Vec<usize> {
-    patterns.iter().map(|p| p.can_combine(towels)).collect()
+    if cfg!(feature = "parallel") {
+        count_options_parallel(patterns, towels)
+    } else {
+        count_options_sequential(patterns, towels)
+    }
+}
+
+fn count_options_sequential(patterns: &[Pattern], towels: &[Towel]) -> Vec<usize> {
+    let trie = TowelTrie::new(towels);
+    patterns.iter().map(|p| p.can_combine(&trie)).collect()
+}
+
+// every thread counts one contiguous chunk, so joining in spawn order keeps the pattern order
+fn count_options_parallel(patterns: &[Pattern], towels: &[Towel]) -> Vec<usize> {
+    let trie = TowelTrie::new(towels);
+    let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = patterns.len().div_ceil(n_threads).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = patterns
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let trie = &trie;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|p| p.can_combine(trie))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("counting thread panicked"))
+            .collect()
+    })
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 19");
     let path = PathBuf::from("./resources/day19.txt");
     let data = util::get_data_string(&path)?;
     let (towels, patterns) = parse_stripes(&data).unwrap();
     let options = count_options(&patterns, &towels);
-    let possible = options.iter().filter(|&&v| v > 0).count();
-    println!("can create {possible} patterns");
+    if part.one() {
+        let possible = options.iter().filter(|&&v| v > 0).count();
+        println!("can create {possible} patterns");
+    }
 
-    let arrangements: usize = options.iter().sum();
-    println!("we have {arrangements} options to create patterns");
+    if part.two() {
+        let arrangements: usize = options.iter().sum();
+        println!("we have {arrangements} options to create patterns");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        19
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (towels, patterns) = parse_stripes(&data)?;
+        let options = count_options(&patterns, &towels);
+        Ok(options.iter().filter(|&&v| v > 0).count().to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (towels, patterns) = parse_stripes(&data)?;
+        let options = count_options(&patterns, &towels);
+        Ok(options.iter().sum::<usize>().to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,7 +263,62 @@ brgr
 bbrgwb";
         let (towels, patterns) = parse_stripes(input).unwrap();
         let options = count_options(&patterns, &towels);
+        assert_eq!(options, vec![2, 1, 4, 6, 0, 1, 2, 0]);
         let arrangements: usize = options.iter().sum();
         assert_eq!(arrangements, 16);
     }
+
+    #[test]
+    fn test_decompose_pattern() {
+        let input = "r, wr, b, g, bwu, rb, gb, br
+
+brwrr
+ubwu";
+        let (towels, patterns) = parse_stripes(input).unwrap();
+        let indices = patterns[0].decompose(&towels).unwrap();
+        let stripes: Vec<Stripe> = indices
+            .iter()
+            .flat_map(|&i| towels[i].stripes.iter().copied())
+            .collect();
+        assert_eq!(stripes, patterns[0].stripes);
+        assert_eq!(patterns[1].decompose(&towels), None);
+
+        let empty = Pattern { stripes: vec![] };
+        assert_eq!(empty.decompose(&towels), Some(vec![]));
+    }
+
+    #[test]
+    fn test_parse_new_colors() {
+        let input = "r, wr, yb, y
+
+yyb
+ywr
+yx";
+        let (towels, patterns) = parse_stripes(input).unwrap();
+        assert_eq!(towels[2].stripes, vec![Stripe(b'y'), Stripe(b'b')]);
+        let options = count_options(&patterns, &towels);
+        assert_eq!(options, vec![1, 1, 0]);
+
+        assert!(parse_stripes("r, w1\n\nrw").is_err());
+        assert!(parse_stripes("r, W\n\nrw").is_err());
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let input = "r, wr, b, g, bwu, rb, gb, br
+
+brwrr
+bggr
+gbbr
+rrbgbr
+ubwu
+bwurrg
+brgr
+bbrgwb";
+        let (towels, patterns) = parse_stripes(input).unwrap();
+        let sequential = count_options_sequential(&patterns, &towels);
+        let parallel = count_options_parallel(&patterns, &towels);
+        assert_eq!(parallel, sequential);
+        assert_eq!(count_options_parallel(&[], &towels), vec![]);
+    }
 }
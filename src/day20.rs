@@ -1,9 +1,15 @@
 use anyhow::Result;
-use std::{collections::VecDeque, ops::RangeInclusive, path::PathBuf};
+#[cfg(test)]
+use std::cell::Cell;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 struct Pos(usize, usize);
 
 #[derive(Copy, Clone, Debug)]
@@ -20,6 +26,11 @@ struct RaceTrack {
     start: Pos,
     end: Pos,
     tiles: Vec<Tile>,
+    // track cells ordered from start to end
+    path: Vec<Pos>,
+    // how often the track was walked cell by cell
+    #[cfg(test)]
+    traversals: Cell<usize>,
 }
 
 impl RaceTrack {
@@ -71,8 +82,12 @@ impl RaceTrack {
             start,
             end,
             tiles,
+            path: Vec::new(),
+            #[cfg(test)]
+            traversals: Cell::new(0),
         };
         race_track.calculate_distances();
+        race_track.path = race_track.iter_pos().collect();
         Ok(race_track)
     }
 
@@ -143,15 +158,21 @@ impl RaceTrack {
         }
     }
 
-    fn iter_pos(&self) -> TrackPosIter {
+    fn iter_pos(&self) -> TrackPosIter<'_> {
+        #[cfg(test)]
+        self.traversals.set(self.traversals.get() + 1);
         TrackPosIter::new(self)
     }
 
+    fn path_positions(&self) -> &[Pos] {
+        &self.path
+    }
+
     fn iter_circle(&self, origin: Pos, radius: usize) -> CircleIter {
         CircleIter::new(self.size, origin, radius)
     }
 
-    fn iter_cheats(&self, length: usize) -> CheatsIter {
+    fn iter_cheats(&self, length: usize) -> CheatsIter<'_> {
         CheatsIter::new(self, length)
     }
 }
@@ -279,12 +300,15 @@ struct Cheat {
     saving: usize,
 }
 
+// every (start, end) pair is yielded at most once: the track visits each start once, and the
+// circles of different radii around it are disjoint since they hold exactly the cells at that
+// manhattan distance
 struct CheatsIter<'a> {
     track: &'a RaceTrack,
     length: usize,
     last_pos: Option<Pos>,
     last_radius: Option<usize>,
-    track_iter: TrackPosIter<'a>,
+    path_index: usize,
     radius_iter: RangeInclusive<usize>,
     circle_iter: CircleIter,
 }
@@ -292,8 +316,8 @@ struct CheatsIter<'a> {
 impl<'a> CheatsIter<'a> {
     fn new(track: &'a RaceTrack, length: usize) -> Self {
         assert!(length > 0);
-        let mut track_iter = track.iter_pos();
-        let last_pos = track_iter.next();
+        let path_index = 0;
+        let last_pos = track.path_positions().first().copied();
 
         let mut radius_iter = 1..=length;
         let last_radius = radius_iter.next();
@@ -308,7 +332,7 @@ impl<'a> CheatsIter<'a> {
             length,
             last_pos,
             last_radius,
-            track_iter,
+            path_index,
             radius_iter,
             circle_iter,
         }
@@ -350,7 +374,8 @@ impl<'a> CheatsIter<'a> {
     }
 
     fn update_pos(&mut self) {
-        self.last_pos = self.track_iter.next();
+        self.path_index += 1;
+        self.last_pos = self.track.path_positions().get(self.path_index).copied();
         if self.last_pos.is_some() {
             self.radius_iter = 1..=self.length;
         }
@@ -386,23 +411,63 @@ where
         .count()
 }
 
-pub fn run() -> Result<()> {
+// cheats of at most `length` picoseconds that save at least `min_saving`
+fn count_cheats(track: &RaceTrack, length: usize, min_saving: usize) -> usize {
+    count_good_cheats(track.iter_cheats(length), min_saving)
+}
+
+// how many cheats of at most `length` picoseconds achieve each saving
+#[allow(dead_code)]
+fn savings_histogram(track: &RaceTrack, length: usize) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for Cheat { saving, .. } in track.iter_cheats(length) {
+        *histogram.entry(saving).or_default() += 1;
+    }
+    histogram
+}
+
+const MIN_SAVING: usize = 100;
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 20");
     let path = PathBuf::from("./resources/day20.txt");
     let data = util::get_data_string(&path)?;
     let track = RaceTrack::parse(&data)?;
-    let cheat_iter = track.iter_cheats(2);
-    let good_cheats = count_good_cheats(cheat_iter, 100);
-    println!("good 2 picosecond cheating spots: {good_cheats}");
-    let cheat_iter = track.iter_cheats(20);
-    let good_cheats = count_good_cheats(cheat_iter, 100);
-    println!("good 20 picosecond cheating spots: {good_cheats}");
+    if part.one() {
+        let good_cheats = count_cheats(&track, 2, MIN_SAVING);
+        println!("good 2 picosecond cheating spots: {good_cheats}");
+    }
+    if part.two() {
+        let good_cheats = count_cheats(&track, 20, MIN_SAVING);
+        println!("good 20 picosecond cheating spots: {good_cheats}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        20
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let track = RaceTrack::parse(&data)?;
+        Ok(count_cheats(&track, 2, MIN_SAVING).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let track = RaceTrack::parse(&data)?;
+        Ok(count_cheats(&track, 20, MIN_SAVING).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_small_cheats() {
@@ -505,4 +570,158 @@ mod tests {
         assert_eq!(iter.next(), Some(Pos(10, 13)));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_cheats_unique() {
+        let input = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let track = RaceTrack::parse(input).unwrap();
+        for length in [1, 2, 3, 20, 30] {
+            let mut seen = HashSet::new();
+            for Cheat { start, end, .. } in track.iter_cheats(length) {
+                assert!(
+                    seen.insert((start, end)),
+                    "{start:?} -> {end:?} yielded twice"
+                );
+            }
+        }
+        assert_eq!(track.iter_cheats(2).count(), 44);
+
+        for radius in 0..4 {
+            let mut seen = HashSet::new();
+            for pos in CircleIter::new(Pos(5, 5), Pos(1, 2), radius) {
+                assert!(seen.insert(pos));
+            }
+        }
+    }
+
+    #[test]
+    fn test_savings_histogram() {
+        let input = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let track = RaceTrack::parse(input).unwrap();
+        let small = savings_histogram(&track, 2);
+        let expected = BTreeMap::from([
+            (2, 14),
+            (4, 14),
+            (6, 2),
+            (8, 4),
+            (10, 2),
+            (12, 3),
+            (20, 1),
+            (36, 1),
+            (38, 1),
+            (40, 1),
+            (64, 1),
+        ]);
+        assert_eq!(small, expected);
+
+        let large = savings_histogram(&track, 20).split_off(&50);
+        let expected = BTreeMap::from([
+            (50, 32),
+            (52, 31),
+            (54, 29),
+            (56, 39),
+            (58, 25),
+            (60, 23),
+            (62, 20),
+            (64, 19),
+            (66, 12),
+            (68, 14),
+            (70, 12),
+            (72, 22),
+            (74, 4),
+            (76, 3),
+        ]);
+        assert_eq!(large, expected);
+    }
+
+    #[test]
+    fn test_path_walked_once() {
+        let input = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let track = RaceTrack::parse(input).unwrap();
+        assert_eq!(track.traversals.get(), 1);
+
+        let path = track.path_positions();
+        assert_eq!(path.len(), 85);
+        assert_eq!(path.first(), Some(&track.start));
+        assert_eq!(path.last(), Some(&track.end));
+
+        assert_eq!(count_cheats(&track, 2, 1), 44);
+        assert_eq!(count_cheats(&track, 20, 50), 285);
+        assert_eq!(track.traversals.get(), 1);
+        assert!(track.iter_pos().eq(path.iter().copied()));
+    }
+
+    #[test]
+    fn test_count_cheats_min_saving() {
+        let input = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let track = RaceTrack::parse(input).unwrap();
+        assert_eq!(count_cheats(&track, 2, 0), 44);
+        assert_eq!(count_cheats(&track, 2, 20), 5);
+        assert_eq!(count_cheats(&track, 2, 65), 0);
+        assert_eq!(count_cheats(&track, 20, 76), 3);
+        assert_eq!(count_cheats(&track, 20, 74), 7);
+
+        let Cheat { start, end, saving } = track
+            .iter_cheats(2)
+            .max_by_key(|cheat| cheat.saving)
+            .unwrap();
+        assert_eq!(saving, 64);
+        assert_eq!(start.0.abs_diff(end.0) + start.1.abs_diff(end.1), 2);
+    }
 }
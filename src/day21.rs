@@ -2,10 +2,10 @@ use anyhow::Result;
 use std::{
     cmp::Ordering,
     fmt::{Display, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Pos(usize, usize);
@@ -437,33 +437,37 @@ impl NumpadSequence {
     }
 }
 
-fn get_button_count(num_seq: &NumpadSequence, indirection: usize) -> usize {
+const ID_MAPPING: [DirpadButton; 5] = {
     use DirpadButton::*;
-    const ID_MAPPING: [DirpadButton; 5] = [ButtonA, ButtonU, ButtonR, ButtonD, ButtonL];
-    const START_GOAL_COMBIS: usize = ID_MAPPING.len() * ID_MAPPING.len();
+    [ButtonA, ButtonU, ButtonR, ButtonD, ButtonL]
+};
+const START_GOAL_COMBIS: usize = ID_MAPPING.len() * ID_MAPPING.len();
+
+fn get_path_cost(path: &[DirpadButton], move_cost: &[usize]) -> usize {
+    path.windows(2)
+        .flat_map(<&[DirpadButton; 2]>::try_from)
+        .map(|&[s, g]| {
+            let s_id = ID_MAPPING
+                .iter()
+                .position(|&b| b == s)
+                .expect("all buttons in mapping");
+            let g_id = ID_MAPPING
+                .iter()
+                .position(|&b| b == g)
+                .expect("all buttons in mapping");
+            let index = s_id * ID_MAPPING.len() + g_id;
+            move_cost[index]
+        })
+        .sum()
+}
 
+// point to point costs on the dirpad for every level up to `indirection`,
+// level n is the cost when n robots sit between the user and this dirpad
+fn build_cost_table(indirection: usize) -> Vec<[usize; START_GOAL_COMBIS]> {
     let mut table = vec![[0; START_GOAL_COMBIS]; indirection + 1];
-    for i in 0..25 {
-        table[0][i] = 1;
-    }
-
-    fn get_path_cost(path: &[DirpadButton], move_cost: &[usize]) -> usize {
-        path.windows(2)
-            .flat_map(<&[DirpadButton; 2]>::try_from)
-            .map(|&[s, g]| {
-                let s_id = ID_MAPPING
-                    .iter()
-                    .position(|&b| b == s)
-                    .expect("all buttons in mapping");
-                let g_id = ID_MAPPING
-                    .iter()
-                    .position(|&b| b == g)
-                    .expect("all buttons in mapping");
-                let index = s_id * ID_MAPPING.len() + g_id;
-                move_cost[index]
-            })
-            .sum()
-    }
+    debug_assert_eq!(table[0].len(), START_GOAL_COMBIS);
+    // the user presses every button directly
+    table[0].fill(1);
 
     // create all point to point costs for all levels starting from user
     for level in 1..=indirection {
@@ -480,9 +484,12 @@ fn get_button_count(num_seq: &NumpadSequence, indirection: usize) -> usize {
             }
         }
     }
+    table
+}
 
+fn get_button_count_with(num_seq: &NumpadSequence, move_cost: &[usize]) -> usize {
     // final movement taken decided by numpad sequence
-    let NumpadSequence(num_seq) = num_seq.clone();
+    let NumpadSequence(num_seq) = num_seq;
 
     num_seq
         .windows(2)
@@ -490,16 +497,66 @@ fn get_button_count(num_seq: &NumpadSequence, indirection: usize) -> usize {
         .map(|&[start, goal]| {
             start
                 .iter_paths(goal)
-                .map(|DirpadSequence(path)| get_path_cost(&path, &table[indirection]))
+                .map(|DirpadSequence(path)| get_path_cost(&path, move_cost))
                 .min()
                 .expect("at least one path exists")
         })
         .sum()
 }
 
-fn compute_total_complexity(seq: &NumpadSequence, indirection: usize) -> usize {
+fn cheapest_path<T: GridGraph>(start: T, goal: T, move_cost: &[usize]) -> DirpadSequence {
+    start
+        .iter_paths(goal)
+        .min_by_key(|DirpadSequence(path)| get_path_cost(path, move_cost))
+        .expect("at least one path exists")
+}
+
+// replace every move of `path` by the cheapest moves one robot closer to the user
+fn expand_path(
+    path: &[DirpadButton],
+    level: usize,
+    table: &[[usize; START_GOAL_COMBIS]],
+    out: &mut Vec<DirpadButton>,
+) {
+    if level == 0 {
+        // skip the initial A, the arm is already resting there
+        out.extend_from_slice(&path[1..]);
+        return;
+    }
+    for &[start, goal] in path.windows(2).flat_map(<&[DirpadButton; 2]>::try_from) {
+        let DirpadSequence(sub_path) = cheapest_path(start, goal, &table[level - 1]);
+        expand_path(&sub_path, level - 1, table, out);
+    }
+}
+
+// the presses the user has to make, grows exponentially with `indirection`
+#[allow(dead_code)]
+fn cheapest_sequence(num_seq: &NumpadSequence, indirection: usize) -> DirpadSequence {
+    let table = build_cost_table(indirection);
+    let NumpadSequence(num_seq) = num_seq;
+
+    let mut out = vec![DirpadButton::ButtonA];
+    for &[start, goal] in num_seq.windows(2).flat_map(<&[NumpadButton; 2]>::try_from) {
+        let DirpadSequence(path) = cheapest_path(start, goal, &table[indirection]);
+        expand_path(&path, indirection, &table, &mut out);
+    }
+    DirpadSequence(out)
+}
+
+#[allow(dead_code)]
+fn get_button_count(num_seq: &NumpadSequence, indirection: usize) -> usize {
+    let table = build_cost_table(indirection);
+    get_button_count_with(num_seq, &table[indirection])
+}
+
+// `table` has to reach at least up to `indirection`, see `build_cost_table`
+fn compute_total_complexity(
+    seq: &NumpadSequence,
+    table: &[[usize; START_GOAL_COMBIS]],
+    indirection: usize,
+) -> usize {
     let code_value = seq.code_value();
-    let button_count = get_button_count(seq, indirection);
+    let button_count = get_button_count_with(seq, &table[indirection]);
     code_value * button_count
 }
 
@@ -511,24 +568,60 @@ fn parse_sequences(input: &str) -> Result<Vec<NumpadSequence>> {
         .collect()
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 21");
     let path = PathBuf::from("./resources/day21.txt");
     let data = util::get_data_string(&path)?;
     let sequences = parse_sequences(&data)?;
-    let with_few_indirections: usize = sequences
-        .iter()
-        .map(|s| compute_total_complexity(s, 2))
-        .sum();
-    println!("sum of complexities with 3 robots: {with_few_indirections}");
-    let with_many_indirections: usize = sequences
-        .iter()
-        .map(|s| compute_total_complexity(s, 25))
-        .sum();
-    println!("sum of complexities with 26 robots: {with_many_indirections}");
+    // the deeper table contains all shallower levels
+    let table = build_cost_table(25);
+    if part.one() {
+        let with_few_indirections: usize = sequences
+            .iter()
+            .map(|s| compute_total_complexity(s, &table, 2))
+            .sum();
+        println!("sum of complexities with 3 robots: {with_few_indirections}");
+    }
+    if part.two() {
+        let with_many_indirections: usize = sequences
+            .iter()
+            .map(|s| compute_total_complexity(s, &table, 25))
+            .sum();
+        println!("sum of complexities with 26 robots: {with_many_indirections}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        21
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let sequences = parse_sequences(&data)?;
+        let table = build_cost_table(2);
+        let total: usize = sequences
+            .iter()
+            .map(|s| compute_total_complexity(s, &table, 2))
+            .sum();
+        Ok(total.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let sequences = parse_sequences(&data)?;
+        let table = build_cost_table(25);
+        let total: usize = sequences
+            .iter()
+            .map(|s| compute_total_complexity(s, &table, 25))
+            .sum();
+        Ok(total.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -541,11 +634,35 @@ mod tests {
 456A
 379A";
         let sequences = parse_sequences(input).unwrap();
+        let table = build_cost_table(2);
         let total_complexity: usize = sequences
             .iter()
-            .map(|s| compute_total_complexity(s, 2))
+            .map(|s| compute_total_complexity(s, &table, 2))
             .sum();
         assert_eq!(total_complexity, 126384);
+
+        // shallower levels of a deeper table are the same as building them directly
+        let deep_table = build_cost_table(25);
+        assert_eq!(deep_table[..=2], table[..]);
+        let total_complexity: usize = sequences
+            .iter()
+            .map(|s| compute_total_complexity(s, &deep_table, 2))
+            .sum();
+        assert_eq!(total_complexity, 126384);
+        assert_eq!(get_button_count(&sequences[0], 2), 68);
+    }
+
+    #[test]
+    fn test_base_level_costs() {
+        let table = build_cost_table(1);
+        assert_eq!(table[0], [1; START_GOAL_COMBIS]);
+        // moving to the same button is just the activation
+        for id in 0..ID_MAPPING.len() {
+            assert_eq!(table[1][id * ID_MAPPING.len() + id], 1);
+        }
+        let sequence = NumpadSequence::parse("029A").unwrap();
+        assert_eq!(get_button_count(&sequence, 0), 12);
+        assert_eq!(get_button_count(&sequence, 1), 28);
     }
 
     #[test]
@@ -555,4 +672,122 @@ mod tests {
         let iter = start.iter_paths(goal);
         assert_eq!(iter.count(), 2);
     }
+
+    // move `pos` along the button presses of `path`, calling `visit` on every position passed
+    fn walk_positions(pos: Pos, path: &DirpadSequence, mut visit: impl FnMut(Pos)) -> Pos {
+        use DirpadButton::*;
+        let DirpadSequence(buttons) = path;
+        let Pos(mut x, mut y) = pos;
+        for b in buttons {
+            match b {
+                ButtonU => y -= 1,
+                ButtonR => x += 1,
+                ButtonD => y += 1,
+                ButtonL => x -= 1,
+                ButtonA => continue,
+            }
+            visit(Pos(x, y));
+        }
+        Pos(x, y)
+    }
+
+    fn check_gap_avoided<T: GridGraph>(buttons: &[T], size: Pos, gap: Pos) {
+        use Direction::*;
+        let Pos(width, height) = size;
+        // moving is only possible onto real buttons, never onto the gap
+        for &b in buttons {
+            let Pos(x, y) = b.get_pos();
+            for (dir, target) in [
+                (Up, y.checked_sub(1).map(|y| Pos(x, y))),
+                (Right, Some(Pos(x + 1, y)).filter(|p| p.0 < width)),
+                (Down, Some(Pos(x, y + 1)).filter(|p| p.1 < height)),
+                (Left, x.checked_sub(1).map(|x| Pos(x, y))),
+            ] {
+                let target = target.filter(|&p| p != gap);
+                assert_eq!(b.go(dir).ok().map(T::get_pos), target, "{b:?} {dir:?}");
+            }
+        }
+        // so no generated path ever passes the gap
+        for &start in buttons {
+            for &goal in buttons {
+                for path in start.iter_paths(goal) {
+                    let end = walk_positions(start.get_pos(), &path, |p| assert_ne!(p, gap));
+                    assert_eq!(end, goal.get_pos());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_paths_avoid_gap() {
+        use DirpadButton::*;
+        use NumpadButton::*;
+        let numpad = [
+            NumpadButton::ButtonA,
+            Button0,
+            Button1,
+            Button2,
+            Button3,
+            Button4,
+            Button5,
+            Button6,
+            Button7,
+            Button8,
+            Button9,
+        ];
+        check_gap_avoided(&numpad, Pos(3, 4), Pos(0, 3));
+        let dirpad = [DirpadButton::ButtonA, ButtonU, ButtonR, ButtonD, ButtonL];
+        check_gap_avoided(&dirpad, Pos(3, 2), Pos(0, 0));
+
+        let paths: Vec<_> = Button7
+            .iter_paths(NumpadButton::ButtonA)
+            .map(|p| p.to_string())
+            .collect();
+        assert!(!paths.is_empty());
+        assert!(!paths.contains(&"vvv>>A".to_string()));
+        let paths: Vec<_> = ButtonL
+            .iter_paths(DirpadButton::ButtonA)
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(paths, vec![">^>A", ">>^A"]);
+    }
+
+    // press `presses` on a keypad whose arm starts at `start`, returning the activated buttons
+    fn operate<T: GridGraph>(presses: &[DirpadButton], start: T) -> Vec<T> {
+        use Direction::*;
+        let mut at = start;
+        let mut out = Vec::new();
+        for b in presses {
+            let dir = match b {
+                DirpadButton::ButtonU => Up,
+                DirpadButton::ButtonR => Right,
+                DirpadButton::ButtonD => Down,
+                DirpadButton::ButtonL => Left,
+                DirpadButton::ButtonA => {
+                    out.push(at);
+                    continue;
+                }
+            };
+            at = at.go(dir).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_cheapest_sequence() {
+        let sequence = NumpadSequence::parse("029A").unwrap();
+        for indirection in 0..=3 {
+            let DirpadSequence(presses) = cheapest_sequence(&sequence, indirection);
+            assert_eq!(presses.len() - 1, get_button_count(&sequence, indirection));
+
+            let mut typed = presses[1..].to_vec();
+            for _ in 0..indirection {
+                typed = operate(&typed, DirpadButton::ButtonA);
+            }
+            let typed = operate(&typed, NumpadButton::ButtonA);
+            assert_eq!(typed, sequence.0[1..]);
+        }
+        let cheapest = cheapest_sequence(&sequence, 2);
+        assert_eq!(cheapest.to_string().len(), 68);
+    }
 }
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{cmp::Ordering, path::PathBuf};
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct Secret(usize);
@@ -42,6 +45,23 @@ impl Secret {
         let &Secret(val) = self;
         val % 10
     }
+
+    // the current price followed by the prices of the next `n` evolutions
+    fn prices(self, n: usize) -> impl Iterator<Item = usize> {
+        std::iter::successors(Some(self), |s| Some(s.evolve()))
+            .take(n + 1)
+            .map(|s| s.make_banana_price())
+    }
+
+    // the `n` price changes between consecutive evolutions
+    fn changes(self, n: usize) -> impl Iterator<Item = isize> {
+        let mut prices = self.prices(n);
+        let first = prices.next();
+        prices.scan(first, |prev, cur| {
+            let before = prev.replace(cur)?;
+            Some(get_change(before, cur))
+        })
+    }
 }
 
 fn parse_secrets(input: &str) -> Result<Vec<Secret>> {
@@ -52,11 +72,39 @@ fn parse_secrets(input: &str) -> Result<Vec<Secret>> {
         .collect()
 }
 
+// split `secrets` into one contiguous chunk per thread and collect the chunk results in order
+fn map_chunks_parallel<T, F>(secrets: &[Secret], f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&[Secret]) -> T + Sync,
+{
+    let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = secrets.len().div_ceil(n_threads).max(1);
+    std::thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = secrets
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("buyer thread panicked"))
+            .collect()
+    })
+}
+
 fn sum_last_secrets(secrets: &[Secret], times: usize) -> usize {
-    secrets
-        .iter()
-        .map(|s| s.evolve_many(times).0)
-        .sum::<usize>()
+    let sum_chunk = |secrets: &[Secret]| {
+        secrets
+            .iter()
+            .map(|s| s.evolve_many(times).0)
+            .sum::<usize>()
+    };
+    if cfg!(feature = "parallel") {
+        map_chunks_parallel(secrets, sum_chunk).into_iter().sum()
+    } else {
+        sum_chunk(secrets)
+    }
 }
 
 struct ChangeSequence {
@@ -70,7 +118,7 @@ impl ChangeSequence {
         Self { size, storage }
     }
     fn push(&mut self, value: isize) {
-        if self.storage.len() == 4 {
+        if self.storage.len() == self.size {
             self.storage.remove(0);
         }
         self.storage.push(value);
@@ -87,73 +135,177 @@ impl ChangeSequence {
 
 fn get_change(before: usize, after: usize) -> isize {
     match before.cmp(&after) {
-        Ordering::Less => -(before.abs_diff(after) as isize),
-        Ordering::Equal | Ordering::Greater => before.abs_diff(after) as isize,
+        Ordering::Less | Ordering::Equal => before.abs_diff(after) as isize,
+        Ordering::Greater => -(before.abs_diff(after) as isize),
     }
 }
 
-fn find_best_banana_bargain(secrets: &[Secret], seq_size: usize, changes: usize) -> usize {
-    if seq_size > changes {
-        return 0;
+const CHANGE_RANGE: usize = 9 + 1 + 9;
+
+fn make_key(key: &[isize]) -> usize {
+    key.iter().fold(0, |acc, v| {
+        assert!(v.abs() < 10);
+        acc * CHANGE_RANGE + ((v + 9) as usize)
+    })
+}
+
+// inverse of `make_key`
+fn decode_key(mut key: usize, seq: &mut [isize]) {
+    for v in seq.iter_mut().rev() {
+        *v = (key % CHANGE_RANGE) as isize - 9;
+        key /= CHANGE_RANGE;
     }
+}
 
-    const CHANGE_RANGE: usize = 9 + 1 + 9;
-    let unique_seqs = CHANGE_RANGE.pow(seq_size as u32);
+// remembers which sequences a buyer already used, reused across buyers by stamping each slot
+// with the buyer generation instead of clearing it
+struct SeenBuffer {
+    stamps: Vec<u32>,
+    generation: u32,
+}
 
-    let make_key = |key: &[isize]| -> usize {
-        key.iter().fold(0, |acc, v| {
-            assert!(v.abs() < 10);
-            acc * CHANGE_RANGE + ((v + 9) as usize)
-        })
-    };
+impl SeenBuffer {
+    fn new(size: usize) -> Self {
+        let stamps = vec![0; size];
+        Self {
+            stamps,
+            generation: 0,
+        }
+    }
+
+    fn next_buyer(&mut self) {
+        self.generation += 1;
+    }
+
+    // true only the first time `key` is seen for the current buyer
+    fn first_visit(&mut self, key: usize) -> bool {
+        let stamp = &mut self.stamps[key];
+        let first = *stamp != self.generation;
+        *stamp = self.generation;
+        first
+    }
+}
+
+// add the price of the first occurrence of every change sequence of one buyer to `map`
+fn add_buyer_bargains(
+    secret: Secret,
+    seq_size: usize,
+    changes: usize,
+    map: &mut [usize],
+    seen: &mut SeenBuffer,
+) {
+    seen.next_buyer(); // only first occurrence of sequence counts
+    let mut change_sequence = ChangeSequence::new(seq_size);
+    let prices = secret.prices(changes).skip(1);
+
+    for (change, price) in secret.changes(changes).zip(prices) {
+        change_sequence.push(change);
+
+        if let Some(key) = change_sequence.get() {
+            let key = make_key(key);
+
+            if seen.first_visit(key) {
+                map[key] += price;
+            }
+        }
+    }
+}
+
+// total bananas for every change sequence, indexed by `make_key`
+fn bargain_table(secrets: &[Secret], seq_size: usize, changes: usize) -> Vec<usize> {
+    let unique_seqs = CHANGE_RANGE.pow(seq_size as u32);
 
     // save potential result for all sequences
     // use long vecs instead of hash map because hashing takes a long time
     // not viable for longer sequences
-    let mut map = vec![0; unique_seqs];
-    for secret in secrets {
-        let mut set = vec![false; unique_seqs]; // only first occurrence of sequence counts
-        let mut change_sequence = ChangeSequence::new(seq_size);
-        let mut prev = *secret;
-        let mut cur = secret.evolve();
-
-        for _ in 0..changes {
-            let prev_price = prev.make_banana_price();
-            let cur_price = cur.make_banana_price();
-            let change = get_change(prev_price, cur_price);
-            change_sequence.push(change);
-
-            if let Some(key) = change_sequence.get() {
-                let key = make_key(key);
-
-                if !set[key] {
-                    set[key] = true;
-                    map[key] += cur_price;
-                }
-            }
-
-            prev = cur;
-            cur = cur.evolve();
+    let bargains_of = |secrets: &[Secret]| {
+        let mut map = vec![0; unique_seqs];
+        let mut seen = SeenBuffer::new(unique_seqs);
+        for &secret in secrets {
+            add_buyer_bargains(secret, seq_size, changes, &mut map, &mut seen);
         }
+        map
+    };
+    if cfg!(feature = "parallel") {
+        // every thread fills its own table, they are summed afterwards
+        map_chunks_parallel(secrets, bargains_of)
+            .into_iter()
+            .reduce(|mut total, partial| {
+                total.iter_mut().zip(partial).for_each(|(t, p)| *t += p);
+                total
+            })
+            .unwrap_or_else(|| vec![0; unique_seqs])
+    } else {
+        bargains_of(secrets)
     }
+}
 
+fn find_best_banana_bargain(secrets: &[Secret], seq_size: usize, changes: usize) -> usize {
+    if seq_size > changes {
+        return 0;
+    }
+    let map = bargain_table(secrets, seq_size, changes);
     *map.iter()
         .max()
         .expect("has valid sequence because seq_size <= changes")
 }
 
-pub fn run() -> Result<()> {
+// the first sequence of four price changes that yields the most bananas
+#[allow(dead_code)]
+fn best_banana_sequence(
+    secrets: &[Secret],
+    seq_size: usize,
+    changes: usize,
+) -> ([isize; 4], usize) {
+    assert_eq!(seq_size, 4, "only sequences of four changes are supported");
+    assert!(seq_size <= changes);
+    let map = bargain_table(secrets, seq_size, changes);
+    let bananas = *map.iter().max().expect("has at least one sequence");
+    let key = map
+        .iter()
+        .position(|&b| b == bananas)
+        .expect("maximum is in map");
+    let mut seq = [0; 4];
+    decode_key(key, &mut seq);
+    (seq, bananas)
+}
+
+pub fn run(part: Part) -> Result<()> {
     println!("day 22");
     let path = PathBuf::from("./resources/day22.txt");
     let data = util::get_data_string(&path)?;
     let secrets = parse_secrets(&data)?;
-    let total = sum_last_secrets(&secrets, 2000);
-    println!("sum of evolved secret numbers: {total}");
-    let bananas = find_best_banana_bargain(&secrets, 4, 2000);
-    println!("best banana bargain: {bananas}");
+    if part.one() {
+        let total = sum_last_secrets(&secrets, 2000);
+        println!("sum of evolved secret numbers: {total}");
+    }
+    if part.two() {
+        let bananas = find_best_banana_bargain(&secrets, 4, 2000);
+        println!("best banana bargain: {bananas}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        22
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let secrets = parse_secrets(&data)?;
+        Ok(sum_last_secrets(&secrets, 2000).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let secrets = parse_secrets(&data)?;
+        Ok(find_best_banana_bargain(&secrets, 4, 2000).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,6 +357,16 @@ mod tests {
         assert_eq!(secret, Secret(5908254));
     }
 
+    #[test]
+    fn test_prices_and_changes() {
+        let prices: Vec<_> = Secret(123).prices(9).collect();
+        assert_eq!(prices, vec![3, 0, 6, 5, 4, 4, 6, 4, 4, 2]);
+        let changes: Vec<_> = Secret(123).changes(9).collect();
+        assert_eq!(changes, vec![-3, 6, -1, -1, 0, 2, -2, 0, -2]);
+        assert_eq!(Secret(123).prices(0).count(), 1);
+        assert_eq!(Secret(123).changes(0).count(), 0);
+    }
+
     #[test]
     fn test_example_banana_bargain() {
         let input = "1
@@ -214,5 +376,63 @@ mod tests {
         let secrets = parse_secrets(input).unwrap();
         let bananas = find_best_banana_bargain(&secrets, 4, 2000);
         assert_eq!(bananas, 23);
+
+        let (seq, bananas) = best_banana_sequence(&secrets, 4, 2000);
+        assert_eq!(seq, [-2, 1, -1, 3]);
+        assert_eq!(bananas, 23);
+    }
+
+    #[test]
+    fn test_decode_key() {
+        for key in [[-9, 0, 9, 1], [0, 0, 0, 0], [-2, 1, -1, 3], [9, 9, 9, 9]] {
+            let mut seq = [0; 4];
+            decode_key(make_key(&key), &mut seq);
+            assert_eq!(seq, key);
+        }
+        assert_eq!(get_change(3, 0), -3);
+        assert_eq!(get_change(4, 6), 2);
+    }
+
+    #[test]
+    fn test_parallel_chunks_keep_order() {
+        let secrets: Vec<_> = (1..=50).map(Secret).collect();
+        let firsts = map_chunks_parallel(&secrets, |chunk| chunk[0].0);
+        assert_eq!(firsts[0], 1);
+        assert!(firsts.windows(2).all(|w| w[0] < w[1]));
+        let sums: usize = map_chunks_parallel(&secrets, |chunk| chunk.len())
+            .into_iter()
+            .sum();
+        assert_eq!(sums, 50);
+        assert!(map_chunks_parallel(&[], |chunk| chunk.len()).is_empty());
+    }
+
+    #[test]
+    fn test_many_buyers_bargain() {
+        let secrets: Vec<_> = (0..100).map(|i| Secret(i * 7919 + 1)).collect();
+        let bananas = find_best_banana_bargain(&secrets, 4, 2000);
+
+        // same as a fresh buffer for every buyer
+        let unique_seqs = CHANGE_RANGE.pow(4);
+        let mut map = vec![0; unique_seqs];
+        for &secret in &secrets {
+            let mut seen = SeenBuffer::new(unique_seqs);
+            add_buyer_bargains(secret, 4, 2000, &mut map, &mut seen);
+        }
+        assert_eq!(bananas, *map.iter().max().unwrap());
+        // every buyer sells at most once for the best sequence
+        assert!(bananas <= 9 * secrets.len());
+    }
+
+    #[test]
+    fn test_seen_buffer_generations() {
+        let mut seen = SeenBuffer::new(3);
+        seen.next_buyer();
+        assert!(seen.first_visit(1));
+        assert!(!seen.first_visit(1));
+        assert!(seen.first_visit(2));
+        seen.next_buyer();
+        assert!(seen.first_visit(1));
+        assert!(seen.first_visit(0));
+        assert!(!seen.first_visit(0));
     }
 }
@@ -1,253 +1,206 @@
 use anyhow::Result;
 use std::{
-    cell::RefCell,
-    collections::{HashMap, VecDeque},
-    path::PathBuf,
-    rc::{Rc, Weak},
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
 };
 
-use crate::util::{self, AocError};
-
-#[derive(Clone, Debug)]
-struct Computer {
-    name: String,
-    neighbors: Vec<Weak<RefCell<Computer>>>,
-}
-
-impl Eq for Computer {}
-
-impl PartialEq for Computer {
-    fn eq(&self, other: &Self) -> bool {
-        self.name.eq(&other.name)
-    }
-}
-
-impl std::hash::Hash for Computer {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.name.hash(state)
-    }
-}
+use crate::util::{self, AocError, Part, Solution};
 
 struct Network {
-    computers: Vec<Rc<RefCell<Computer>>>,
-}
-
-impl Computer {
-    fn has_neighbor(&self, other: &Computer) -> bool {
-        if other.neighbors.len() < self.neighbors.len() {
-            other
-                .neighbors
-                .iter()
-                .any(|c| *c.upgrade().expect("all neighbors exist").borrow() == *self)
-        } else {
-            self.neighbors
-                .iter()
-                .any(|c| *c.upgrade().expect("all neighbors exist").borrow() == *other)
-        }
-    }
+    // sorted, so indices are deterministic
+    names: Vec<String>,
+    adj: Vec<HashSet<usize>>,
 }
 
 impl Network {
     fn parse(input: &str) -> Result<Self> {
         use AocError::ParseError;
-        let mut computers = HashMap::new();
+        let mut edges = Vec::new();
 
         for s in input.split("\n").filter(|s| !s.is_empty()) {
             let (one, two) = s.trim().split_once("-").ok_or(ParseError)?;
             if two.contains("-") {
                 return Err(ParseError.into());
             }
-            let one = computers.entry(one).or_insert_with(|| {
-                Rc::from(RefCell::from(Computer {
-                    name: one.to_string(),
-                    neighbors: vec![],
-                }))
-            });
-            let one = Rc::downgrade(one);
-
-            let two = computers.entry(two).or_insert_with(|| {
-                Rc::from(RefCell::from(Computer {
-                    name: two.to_string(),
-                    neighbors: vec![],
-                }))
-            });
-            let two = Rc::downgrade(two);
+            edges.push((one, two));
+        }
 
+        let mut names: Vec<String> = edges
+            .iter()
+            .flat_map(|&(one, two)| [one, two])
+            .map(String::from)
+            .collect();
+        names.sort();
+        names.dedup();
+        let indices: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+
+        let mut adj = vec![HashSet::new(); names.len()];
+        for (one, two) in edges {
+            let one = indices[one];
+            let two = indices[two];
             // add each other as neighbor
-            one.upgrade()
-                .expect("all computers in hash map")
-                .borrow_mut()
-                .neighbors
-                .push(two.clone());
-            two.upgrade()
-                .expect("all computers in hash map")
-                .borrow_mut()
-                .neighbors
-                .push(one.clone());
+            adj[one].insert(two);
+            adj[two].insert(one);
         }
+        Ok(Network { names, adj })
+    }
 
-        let mut computers = computers.into_values().collect::<Vec<_>>();
+    // every 3-clique exactly once, as ascending indices
+    fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.adj.iter().enumerate().flat_map(move |(i, neighbors)| {
+            neighbors
+                .iter()
+                .filter(move |&&j| j > i)
+                .flat_map(move |&j| {
+                    self.adj[j]
+                        .iter()
+                        .filter(move |&&k| k > j && neighbors.contains(&k))
+                        .map(move |&k| [i, j, k])
+                })
+        })
+    }
 
-        // make order deterministic
-        computers.sort_by(|a, b| {
-            let a = a.borrow();
-            let b = b.borrow();
-            (a.name.cmp(&b.name)).then((a.neighbors.len()).cmp(&b.neighbors.len()))
-        });
-        Ok(Network { computers })
+    #[allow(dead_code)]
+    fn count_triangles(&self) -> usize {
+        self.triangles().count()
     }
 
+    // 3-cliques with at least one computer matching `filter`
     fn count_filtered_cliques<F>(&self, filter: F) -> usize
     where
-        F: Fn(&Computer) -> bool,
+        F: Fn(&str) -> bool,
     {
-        let mut cliques = Vec::new();
-        for computer in self.computers.iter() {
-            let computer = computer.borrow();
-            if !filter(&computer) {
-                continue;
-            }
-
-            for first in computer.neighbors.iter() {
-                let first = first.upgrade().expect("all computers in hash map");
-                let first = first.borrow();
-
-                for second in computer.neighbors.iter() {
-                    let second = second.upgrade().expect("all computers in hash map");
-                    let second = second.borrow();
-
-                    if first.has_neighbor(&second) {
-                        let mut clique = [
-                            computer.name.clone(),
-                            first.name.clone(),
-                            second.name.clone(),
-                        ];
-                        clique.sort();
-                        if !cliques.contains(&clique) {
-                            cliques.push(clique);
-                        }
-                    }
-                }
-            }
-        }
-        cliques.len()
+        self.triangles()
+            .filter(|clique| clique.iter().any(|&c| filter(&self.names[c])))
+            .count()
     }
 
     fn get_largest_clique(&self) -> Vec<String> {
-        #[derive(Debug)]
-        struct TreeNode {
-            index: usize,
-            clique: Vec<usize>,
-            take: bool,
-        }
-
-        // computers are Rc so clone is cheap
-        let mut sorted = self.computers.clone();
-        sorted.sort_by(|a, b| {
-            let a = a.borrow();
-            let b = b.borrow();
-            a.neighbors.len().cmp(&b.neighbors.len())
-        });
-
-        let mut best = Vec::new();
-        for computer in sorted.iter() {
-            let computer = computer.borrow();
-            let n_neighbors = computer.neighbors.len();
-            if n_neighbors <= best.len() {
-                // no computer after has more neighbors because we sorted
-                break;
-            }
-
-            let mut queue = VecDeque::new();
-            queue.push_back(TreeNode {
-                index: 0,
-                clique: Vec::new(),
-                take: true,
-            });
-            queue.push_back(TreeNode {
-                index: 0,
-                clique: Vec::new(),
-                take: false,
-            });
-
-            while let Some(node) = queue.pop_front() {
-                let TreeNode {
-                    index,
-                    mut clique,
-                    take,
-                } = node;
-
-                let potential_size = n_neighbors - index + 1 + clique.len();
-                if index == n_neighbors || best.len() >= potential_size {
-                    continue;
+        let mut best: Vec<usize> = Vec::new();
+        let all = (0..self.adj.len()).collect();
+        bron_kerbosch(
+            &self.adj,
+            &mut Vec::new(),
+            all,
+            HashSet::new(),
+            &mut |clique| {
+                if clique.len() > best.len() {
+                    best = clique.to_vec();
                 }
+            },
+        );
 
-                if take {
-                    let neighbor = computer.neighbors[index]
-                        .upgrade()
-                        .expect("stays valid in this call");
-                    let neighbor = neighbor.borrow();
-                    let not_connected = clique.iter().any(|&c| {
-                        !computer.neighbors[c]
-                            .upgrade()
-                            .expect("stays valid in this call")
-                            .borrow()
-                            .has_neighbor(&neighbor)
-                    });
-                    if not_connected {
-                        continue;
-                    }
+        let mut best: Vec<String> = best.into_iter().map(|i| self.names[i].clone()).collect();
+        best.sort();
+        best
+    }
 
-                    clique.push(index);
+    // every clique that cannot be extended by another computer, names sorted within and across cliques
+    #[allow(dead_code)]
+    fn maximal_cliques(&self) -> Vec<Vec<String>> {
+        let mut cliques = Vec::new();
+        let all = (0..self.adj.len()).collect();
+        bron_kerbosch(
+            &self.adj,
+            &mut Vec::new(),
+            all,
+            HashSet::new(),
+            &mut |clique| {
+                let mut names: Vec<String> =
+                    clique.iter().map(|&i| self.names[i].clone()).collect();
+                names.sort();
+                cliques.push(names);
+            },
+        );
+        cliques.sort();
+        cliques
+    }
+}
 
-                    // update best clique
-                    if clique.len() + 1 > best.len() {
-                        best = vec![computer.name.clone()];
-                        best.extend(clique.iter().map(|&i| {
-                            computer.neighbors[i]
-                                .upgrade()
-                                .expect("stays valid in this call")
-                                .borrow()
-                                .name
-                                .clone()
-                        }));
-                    }
-                }
+// Bron–Kerbosch with pivoting, calls `report` for every maximal clique that extends `clique`
+// with vertices of `candidates` but none of `excluded`
+fn bron_kerbosch<F>(
+    adj: &[HashSet<usize>],
+    clique: &mut Vec<usize>,
+    mut candidates: HashSet<usize>,
+    mut excluded: HashSet<usize>,
+    report: &mut F,
+) where
+    F: FnMut(&[usize]),
+{
+    if candidates.is_empty() && excluded.is_empty() {
+        report(clique);
+        return;
+    }
 
-                queue.push_back(TreeNode {
-                    index: index + 1,
-                    clique: clique.clone(),
-                    take: true,
-                });
-                queue.push_back(TreeNode {
-                    index: index + 1,
-                    clique,
-                    take: false,
-                });
-            }
-        }
-        best.sort();
-        best
+    // every maximal clique contains the pivot or one of its non-neighbors
+    let pivot = candidates
+        .iter()
+        .chain(excluded.iter())
+        .max_by_key(|&&u| candidates.intersection(&adj[u]).count())
+        .copied()
+        .expect("candidates or excluded not empty");
+    let mut branches: Vec<usize> = candidates.difference(&adj[pivot]).copied().collect();
+    // make order of reported cliques deterministic
+    branches.sort();
+
+    for v in branches {
+        clique.push(v);
+        let next_candidates = candidates.intersection(&adj[v]).copied().collect();
+        let next_excluded = excluded.intersection(&adj[v]).copied().collect();
+        bron_kerbosch(adj, clique, next_candidates, next_excluded, report);
+        clique.pop();
+
+        candidates.remove(&v);
+        excluded.insert(v);
     }
 }
 
-fn starts_with_t(c: &Computer) -> bool {
-    c.name.starts_with("t")
+fn starts_with_t(name: &str) -> bool {
+    name.starts_with("t")
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 23");
     let path = PathBuf::from("./resources/day23.txt");
     let data = util::get_data_string(&path)?;
     let network = Network::parse(&data)?;
-    let count = network.count_filtered_cliques(starts_with_t);
-    println!("Got {count} cliques");
-    let names = network.get_largest_clique();
-    let password = names.join(",");
-    println!("LAN party password: {password}");
+    if part.one() {
+        let count = network.count_filtered_cliques(starts_with_t);
+        println!("Got {count} cliques");
+    }
+    if part.two() {
+        let names = network.get_largest_clique();
+        let password = names.join(",");
+        println!("LAN party password: {password}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        23
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let network = Network::parse(&data)?;
+        Ok(network.count_filtered_cliques(starts_with_t).to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let network = Network::parse(&data)?;
+        Ok(network.get_largest_clique().join(","))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,6 +242,9 @@ td-yn";
         let network = Network::parse(input).unwrap();
         let count = network.count_filtered_cliques(starts_with_t);
         assert_eq!(count, 7);
+        assert_eq!(network.count_triangles(), 12);
+        assert_eq!(network.count_filtered_cliques(|_| true), 12);
+        assert_eq!(network.count_filtered_cliques(|name| name == "co"), 3);
     }
 
     #[test]
@@ -330,4 +286,79 @@ td-yn";
         let password = names.join(",");
         assert_eq!(password, "co,de,ka,ta");
     }
+
+    #[test]
+    fn test_parse_network() {
+        let network = Network::parse("kh-tc\nqp-kh\ntc-kh\n").unwrap();
+        assert_eq!(network.names, vec!["kh", "qp", "tc"]);
+        assert_eq!(network.adj[0], HashSet::from([1, 2]));
+        assert_eq!(network.adj[1], HashSet::from([0]));
+        assert_eq!(network.adj[2], HashSet::from([0]));
+
+        assert!(Network::parse("kh-tc-qp").is_err());
+        assert!(Network::parse("khtc").is_err());
+    }
+
+    #[test]
+    fn test_maximal_cliques() {
+        let input = "kh-tc
+qp-kh
+de-cg
+ka-co
+yn-aq
+qp-ub
+cg-tb
+vc-aq
+tb-ka
+wh-tc
+yn-cg
+kh-ub
+ta-co
+de-co
+tc-td
+tb-wq
+wh-td
+ta-ka
+td-qp
+aq-cg
+wq-ub
+ub-vc
+de-ta
+wq-aq
+wq-vc
+wh-yn
+ka-de
+kh-ta
+co-tc
+wh-qp
+tb-vc
+td-yn";
+        let network = Network::parse(input).unwrap();
+        let cliques = network.maximal_cliques();
+        let contains = |names: &[&str]| cliques.iter().any(|c| c == names);
+
+        assert!(contains(&["co", "de", "ka", "ta"]));
+        let triangles = [
+            ["aq", "cg", "yn"],
+            ["aq", "vc", "wq"],
+            ["kh", "qp", "ub"],
+            ["qp", "td", "wh"],
+            ["tb", "vc", "wq"],
+            ["tc", "td", "wh"],
+            ["td", "wh", "yn"],
+            ["ub", "vc", "wq"],
+        ];
+        for triangle in triangles {
+            assert!(contains(&triangle), "missing {triangle:?}");
+        }
+        // triangles inside the larger clique are not maximal
+        assert!(!contains(&["co", "de", "ka"]));
+        assert_eq!(cliques.iter().filter(|c| c.len() == 3).count(), 8);
+        assert_eq!(cliques.iter().map(Vec::len).max(), Some(4));
+
+        let mut sorted = cliques.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, cliques);
+    }
 }
@@ -1,7 +1,7 @@
 use anyhow::Result;
-use std::{ops::ControlFlow, path::PathBuf};
+use std::path::{Path, PathBuf};
 
-use crate::util::{self, AocError};
+use crate::util::{self, AocError, Part, Solution};
 
 #[derive(Copy, Clone, Debug)]
 enum Signal {
@@ -16,7 +16,7 @@ struct Wire {
 }
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum Operator {
     OR,
     AND,
@@ -35,6 +35,9 @@ struct Gate {
 struct Circuit {
     wires: Vec<Wire>,
     gates: Vec<Gate>,
+    // how many gates computed their output so far
+    #[cfg(test)]
+    evaluations: usize,
 }
 
 impl From<bool> for Signal {
@@ -133,28 +136,99 @@ impl Circuit {
             })
             .collect::<Result<Vec<_>>>()?;
 
-        Ok(Circuit { wires, gates })
+        Ok(Circuit {
+            wires,
+            gates,
+            #[cfg(test)]
+            evaluations: 0,
+        })
     }
 
-    fn single_pass(&mut self) -> ControlFlow<(), ()> {
-        let mut changed = false;
-        for gate in self.gates.iter() {
-            let &Gate {
+    // Kahn's algorithm over the gates, a gate is ready once all drivers of its inputs are done,
+    // gates on or behind a cycle are missing from the order
+    fn gate_order(&self) -> Vec<usize> {
+        let mut remaining_drivers = vec![0; self.wires.len()];
+        let mut readers = vec![Vec::new(); self.wires.len()];
+        for (index, gate) in self.gates.iter().enumerate() {
+            remaining_drivers[gate.output] += 1;
+            readers[gate.input1].push(index);
+            readers[gate.input2].push(index);
+        }
+        let mut pending = self
+            .gates
+            .iter()
+            .map(|g| {
+                [g.input1, g.input2]
+                    .iter()
+                    .filter(|&&w| remaining_drivers[w] > 0)
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let mut ready = (0..self.gates.len())
+            .filter(|&g| pending[g] == 0)
+            .collect::<Vec<_>>();
+
+        let mut order = Vec::with_capacity(self.gates.len());
+        while let Some(index) = ready.pop() {
+            order.push(index);
+            let output = self.gates[index].output;
+            remaining_drivers[output] -= 1;
+            if remaining_drivers[output] > 0 {
+                continue;
+            }
+            for &reader in readers[output].iter() {
+                pending[reader] -= 1;
+                if pending[reader] == 0 {
+                    ready.push(reader);
+                }
+            }
+        }
+        order
+    }
+
+    fn check_acyclic(&self) -> Result<()> {
+        let order = self.gate_order();
+        if order.len() == self.gates.len() {
+            return Ok(());
+        }
+
+        let mut done = vec![false; self.gates.len()];
+        order.iter().for_each(|&g| done[g] = true);
+        let blocked_driver =
+            |wire: usize| (0..self.gates.len()).find(|&g| !done[g] && self.gates[g].output == wire);
+
+        // follow unfinished inputs backwards until a gate repeats, that one lies on a cycle
+        let mut visited = vec![false; self.gates.len()];
+        let mut current = done
+            .iter()
+            .position(|&d| !d)
+            .expect("some gate is not done");
+        while !visited[current] {
+            visited[current] = true;
+            let gate = self.gates[current];
+            current = [gate.input1, gate.input2]
+                .into_iter()
+                .find_map(blocked_driver)
+                .expect("gates not done have a blocked input");
+        }
+        let name = &self.wires[self.gates[current].output].name;
+        Err(AocError::ValueError(format!("wire {name} depends on itself")).into())
+    }
+
+    // one pass over the gates in topological order, so every gate is evaluated at most once
+    fn evaluate(&mut self) {
+        for index in self.gate_order() {
+            let Gate {
                 op,
                 input1,
                 input2,
                 output,
-            } = gate;
+            } = self.gates[index];
 
-            let Wire { signal: input1, .. } = &self.wires[input1];
-            let input1 = match input1 {
-                Signal::Empty => continue,
-                Signal::Active(v) => v,
-            };
-            let Wire { signal: input2, .. } = &self.wires[input2];
-            let input2 = match input2 {
-                Signal::Empty => continue,
-                Signal::Active(v) => v,
+            let (Signal::Active(input1), Signal::Active(input2)) =
+                (self.wires[input1].signal, self.wires[input2].signal)
+            else {
+                continue;
             };
             if self.wires[output].active() {
                 continue;
@@ -166,12 +240,10 @@ impl Circuit {
             };
 
             self.wires[output].signal = res.into();
-            changed |= true;
-        }
-
-        match changed {
-            true => ControlFlow::Continue(()),
-            false => ControlFlow::Break(()),
+            #[cfg(test)]
+            {
+                self.evaluations += 1;
+            }
         }
     }
 
@@ -200,13 +272,90 @@ impl Circuit {
         Ok(value)
     }
 
-    fn propagate_signals(mut self) -> Circuit {
-        while let ControlFlow::Continue(()) = self.single_pass() {
-            self.single_pass();
+    // write `value` into the wires with `prefix`, bit n goes into wire n (x00 is the lowest bit),
+    // all wires driven by gates are cleared so the next evaluation starts over
+    fn set_input(&mut self, prefix: char, value: usize) {
+        for wire in self.wires.iter_mut() {
+            if wire.name.starts_with(prefix) {
+                let bit = wire.name[1..].parse::<u32>().unwrap_or(usize::BITS);
+                let bit = value.checked_shr(bit).unwrap_or(0) & 1;
+                wire.signal = (bit == 1).into();
+            } else if !wire.name.starts_with('x') && !wire.name.starts_with('y') {
+                wire.signal = Signal::Empty;
+            }
         }
+    }
+
+    // run the circuit on the given operands, for an adder this is x + y
+    #[allow(dead_code)]
+    fn add(&mut self, x: usize, y: usize) -> Result<usize> {
+        self.set_input('x', x);
+        self.set_input('y', y);
+        self.evaluate();
+        self.create_value_from_wires('z')
+    }
+
+    fn propagate_signals(mut self) -> Circuit {
+        self.evaluate();
         self
     }
 
+    // assumes the circuit is meant to be a ripple-carry adder of the x and y bits into z,
+    // the outputs of all gates breaking the wiring rules of such an adder are reported
+    fn find_swapped_outputs(&self) -> Vec<String> {
+        let is_input = |wire: usize| {
+            let name = &self.wires[wire].name;
+            name.starts_with('x') || name.starts_with('y')
+        };
+        let is_first_bit = |wire: usize| self.wires[wire].name[1..] == *"00";
+        let last_z = self
+            .filter_wires('z')
+            .first()
+            .map(|w| w.name.clone())
+            .unwrap_or_default();
+
+        // operators of the gates reading each wire
+        let mut consumers = vec![Vec::new(); self.wires.len()];
+        for gate in self.gates.iter() {
+            consumers[gate.input1].push(gate.op);
+            consumers[gate.input2].push(gate.op);
+        }
+        let feeds = |wire: usize, op: Operator| consumers[wire].contains(&op);
+
+        let mut swapped = Vec::new();
+        for gate in self.gates.iter() {
+            let &Gate {
+                op,
+                input1,
+                input2,
+                output,
+            } = gate;
+            let name = &self.wires[output].name;
+            let from_inputs = is_input(input1) && is_input(input2);
+            let first_bit = from_inputs && is_first_bit(input1);
+
+            let misplaced = match op {
+                // the carry out of the last bit is the only z that is not a sum
+                _ if name.starts_with('z') && *name == last_z => !matches!(op, Operator::OR),
+                Operator::XOR if name.starts_with('z') => from_inputs && !first_bit,
+                _ if name.starts_with('z') => true,
+                // an xor of the carry with the half sum has to be a z
+                Operator::XOR if !from_inputs => true,
+                // half sums are combined with the carry, except for the first bit without carry
+                Operator::XOR => first_bit || !feeds(output, Operator::XOR),
+                // partial carries are merged into the full carry
+                Operator::AND => !first_bit && !feeds(output, Operator::OR),
+                Operator::OR => false,
+            };
+            if misplaced {
+                swapped.push(name.clone());
+            }
+        }
+        swapped.sort();
+        swapped.dedup();
+        swapped
+    }
+
     #[allow(dead_code)]
     fn to_graphviz_representation(&self) -> String {
         let comparison = |l: &Wire, r: &Wire| {
@@ -257,26 +406,50 @@ impl Circuit {
     }
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 24");
     let path = PathBuf::from("./resources/day24.txt");
     let data = util::get_data_string(&path)?;
     let circuit = Circuit::parse(&data)?;
-    let out = circuit.clone().propagate_signals();
-    let out = out.create_value_from_wires('z')?;
-    println!("circuit produces {out}");
-
-    // let dot = circuit.to_graphviz_representation();
-    // println!("{dot}");
-    let swapped = String::from("nvh,z37,qdg,z12,vvf,z19,dck,fgn");
-    let mut swapped = swapped.split(",").collect::<Vec<_>>();
-    swapped.sort();
-    let swapped = swapped.join(",");
-
-    println!("swapping involves wires: {swapped} (solved by hand)");
+    circuit.check_acyclic()?;
+    if part.one() {
+        let out = circuit.clone().propagate_signals();
+        let out = out.create_value_from_wires('z')?;
+        println!("circuit produces {out}");
+    }
+
+    if part.two() {
+        // let dot = circuit.to_graphviz_representation();
+        // println!("{dot}");
+        let swapped = circuit.find_swapped_outputs().join(",");
+        println!("swapping involves wires: {swapped}");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        24
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let circuit = Circuit::parse(&data)?;
+        circuit.check_acyclic()?;
+        let out = circuit.propagate_signals();
+        Ok(out.create_value_from_wires('z')?.to_string())
+    }
+
+    fn part2(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let circuit = Circuit::parse(&data)?;
+        circuit.check_acyclic()?;
+        Ok(circuit.find_swapped_outputs().join(","))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -350,7 +523,105 @@ tgd XOR rvg -> z12
 tnw OR pbm -> gnj";
         let circuit = Circuit::parse(input).unwrap();
         let out = circuit.propagate_signals();
+        // one evaluation per gate, no rescans
+        assert_eq!(out.evaluations, out.gates.len());
+        let mut circuit = out.clone();
         let out = out.create_value_from_wires('z').unwrap();
         assert_eq!(out, 2024);
+
+        let x = circuit.create_value_from_wires('x').unwrap();
+        let y = circuit.create_value_from_wires('y').unwrap();
+        assert_eq!((x, y), (0b01101, 0b11111));
+        let other = circuit.add(0, 0).unwrap();
+        assert_ne!(other, 2024);
+        // earlier results do not leak into the next run
+        assert_eq!(circuit.add(x, y).unwrap(), 2024);
+    }
+
+    #[test]
+    fn test_find_swapped_outputs() {
+        let path = PathBuf::from("./resources/day24.txt");
+        let data = util::get_data_string(&path).unwrap();
+        let circuit = Circuit::parse(&data).unwrap();
+        let swapped = circuit.find_swapped_outputs();
+        // solved by hand with the graphviz representation
+        assert_eq!(swapped.join(","), "dck,fgn,nvh,qdg,vvf,z12,z19,z37");
+    }
+
+    #[test]
+    fn test_correct_adder_has_no_swaps() {
+        let input = "x00: 1
+x01: 0
+y00: 1
+y01: 1
+
+x00 XOR y00 -> z00
+x00 AND y00 -> c00
+x01 XOR y01 -> s01
+x01 AND y01 -> a01
+s01 XOR c00 -> z01
+s01 AND c00 -> b01
+a01 OR b01 -> z02";
+        let circuit = Circuit::parse(input).unwrap();
+        assert!(circuit.find_swapped_outputs().is_empty());
+        let mut adder = circuit.clone();
+        for x in 0..4 {
+            for y in 0..4 {
+                assert_eq!(adder.add(x, y).unwrap(), x + y);
+            }
+        }
+        // bits without a wire are dropped
+        assert_eq!(adder.add(5, 0).unwrap(), 1);
+        let out = circuit.propagate_signals();
+        assert_eq!(out.create_value_from_wires('z').unwrap(), 4);
+
+        let swapped = input
+            .replace("-> s01", "-> tmp")
+            .replace("-> z01", "-> s01");
+        let swapped = swapped.replace("-> tmp", "-> z01");
+        let circuit = Circuit::parse(&swapped).unwrap();
+        assert_eq!(circuit.find_swapped_outputs(), vec!["s01", "z01"]);
+    }
+
+    #[test]
+    fn test_long_chain() {
+        // listed backwards, so scanning the gates in input order would only resolve one gate
+        // per pass, while evaluating in dependency order touches every gate exactly once
+        let mut input = String::from("x00: 1\ny00: 1\n\n");
+        input.push_str("w20 XOR y00 -> z00\n");
+        for i in (1..20).rev() {
+            let op = if i % 2 == 0 { "AND" } else { "OR" };
+            input.push_str(&format!("w{i:02} {op} y00 -> w{:02}\n", i + 1));
+        }
+        input.push_str("x00 AND y00 -> w01\n");
+        let circuit = Circuit::parse(&input).unwrap();
+        let n_gates = circuit.gates.len();
+        let out = circuit.propagate_signals();
+        assert!(out.wires.iter().all(Wire::active));
+        assert_eq!(out.evaluations, n_gates);
+        assert_eq!(out.create_value_from_wires('z').unwrap(), 0);
+
+        // a wire without a driver keeps its output empty
+        let input = "x00: 1\n\nx00 AND y00 -> z00";
+        let out = Circuit::parse(input).unwrap().propagate_signals();
+        assert!(out.create_value_from_wires('z').is_err());
+    }
+
+    #[test]
+    fn test_detect_cycle() {
+        let input = "b: 1\n\na AND b -> a\na XOR b -> z00";
+        let circuit = Circuit::parse(input).unwrap();
+        let err = circuit.check_acyclic().unwrap_err();
+        assert_eq!(err.to_string(), "Value Error: wire a depends on itself");
+
+        let input = "x00: 1\n\nx00 OR c -> a\na AND x00 -> b\nb XOR x00 -> c\nc OR x00 -> z00";
+        let circuit = Circuit::parse(input).unwrap();
+        let err = circuit.check_acyclic().unwrap_err().to_string();
+        assert!(["a", "b", "c"]
+            .iter()
+            .any(|w| err.ends_with(&format!("wire {w} depends on itself"))));
+
+        let input = "x00: 1\ny00: 0\n\nx00 AND y00 -> a\na OR a -> z00";
+        assert!(Circuit::parse(input).unwrap().check_acyclic().is_ok());
     }
 }
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use std::{iter::zip, path::PathBuf};
+use std::{
+    iter::zip,
+    path::{Path, PathBuf},
+};
 
-use crate::util::{self};
+use crate::util::{self, AocError, Part, Solution};
 
 struct Lock {
     heights: [usize; 5],
@@ -25,10 +28,10 @@ fn parse_locks_and_keys(input: &str) -> Result<(Vec<Lock>, Vec<Key>)> {
 
         let mut heights = [0; 5];
 
-        for x in 0..5 {
-            for y in 1..6 {
-                if map[y][x] == '#' {
-                    heights[x] += 1;
+        for row in &map[1..6] {
+            for (height, &c) in zip(&mut heights, row) {
+                if c == '#' {
+                    *height += 1;
                 }
             }
         }
@@ -59,16 +62,37 @@ fn count_overlap_free_pairings(locks: &[Lock], keys: &[Key]) -> usize {
     count
 }
 
-pub fn run() -> Result<()> {
+pub fn run(part: Part) -> Result<()> {
     println!("day 25");
     let path = PathBuf::from("./resources/day25.txt");
     let data = util::get_data_string(&path)?;
     let (locks, keys) = parse_locks_and_keys(&data).unwrap();
-    let pairings = count_overlap_free_pairings(&locks, &keys);
-    println!("got {pairings} many key-lock pairings");
+    if part.one() {
+        let pairings = count_overlap_free_pairings(&locks, &keys);
+        println!("got {pairings} many key-lock pairings");
+    }
     Ok(())
 }
 
+pub struct Solver;
+
+impl Solution for Solver {
+    fn day(&self) -> u8 {
+        25
+    }
+
+    fn part1(&self, path: &Path) -> Result<String> {
+        let data = util::get_data_string(path)?;
+        let (locks, keys) = parse_locks_and_keys(&data)?;
+        Ok(count_overlap_free_pairings(&locks, &keys).to_string())
+    }
+
+    fn part2(&self, _path: &Path) -> Result<String> {
+        // the last day only has one puzzle
+        Err(AocError::ValueError("day 25 has no second part".into()).into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use util::{AocError, Part, Solution};
 
 mod util;
 
@@ -28,34 +29,126 @@ mod day23;
 mod day24;
 mod day25;
 
+const SOLUTIONS: [&dyn Solution; 25] = [
+    &day01::Solver,
+    &day02::Solver,
+    &day03::Solver,
+    &day04::Solver,
+    &day05::Solver,
+    &day06::Solver,
+    &day07::Solver,
+    &day08::Solver,
+    &day09::Solver,
+    &day10::Solver,
+    &day11::Solver,
+    &day12::Solver,
+    &day13::Solver,
+    &day14::Solver,
+    &day15::Solver,
+    &day16::Solver,
+    &day17::Solver,
+    &day18::Solver,
+    &day19::Solver,
+    &day20::Solver,
+    &day21::Solver,
+    &day22::Solver,
+    &day23::Solver,
+    &day24::Solver,
+    &day25::Solver,
+];
+
+struct Args {
+    part: Part,
+    verify: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut part = Part::Both;
+    let mut verify = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "--part" => {
+                let value = args
+                    .next()
+                    .ok_or(AocError::ValueError("--part needs a value".into()))?;
+                part = value.parse()?;
+            }
+            _ => return Err(AocError::ValueError(format!("unknown argument: {arg}")).into()),
+        }
+    }
+    Ok(Args { part, verify })
+}
+
+// check every day against the answers in resources/answers.toml
+fn verify_answers() -> Result<()> {
+    let mismatches = util::verify_all(&SOLUTIONS)?;
+    for mismatch in &mismatches {
+        println!("{mismatch}");
+    }
+    if !mismatches.is_empty() {
+        let s = format!("{} answers no longer match", mismatches.len());
+        return Err(AocError::ValueError(s).into());
+    }
+    println!("all answers match");
+    Ok(())
+}
+
 fn main() -> Result<()> {
+    let Args { part, verify } = parse_args()?;
+    if verify {
+        return verify_answers();
+    }
     #[allow(dead_code)]
     if false {
-        day01::run()?;
-        day02::run()?;
-        day03::run()?;
-        day04::run()?;
-        day05::run()?;
-        day06::run()?;
-        day07::run()?;
-        day08::run()?;
-        day09::run()?;
-        day10::run()?;
-        day11::run()?;
-        day12::run()?;
-        day13::run()?;
-        day14::run()?;
-        day15::run()?;
-        day16::run()?;
-        day17::run()?;
-        day18::run()?;
-        day19::run()?;
-        day20::run()?;
-        day21::run()?;
-        day22::run()?;
-        day23::run()?;
-        day24::run()?;
+        day01::run(part)?;
+        day02::run(part)?;
+        day03::run(part)?;
+        day04::run(part)?;
+        day05::run(part)?;
+        day06::run(part)?;
+        day07::run(part)?;
+        day08::run(part)?;
+        day09::run(part)?;
+        day10::run(part)?;
+        day11::run(part)?;
+        day12::run(part)?;
+        day13::run(part)?;
+        day14::run(part)?;
+        day15::run(part)?;
+        day16::run(part)?;
+        day17::run(part)?;
+        day18::run(part)?;
+        day19::run(part)?;
+        day20::run(part)?;
+        day21::run(part)?;
+        day22::run(part)?;
+        day23::run(part)?;
+        day24::run(part)?;
     }
-    day25::run()?;
+    day25::run(part)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answers_match() {
+        let mismatches = util::verify_all(&SOLUTIONS).unwrap();
+        let report = mismatches
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(mismatches.is_empty(), "answers drifted:\n{report}");
+    }
+
+    #[test]
+    fn test_solutions_cover_every_day() {
+        let days = SOLUTIONS.iter().map(|s| s.day()).collect::<Vec<_>>();
+        assert_eq!(days, (1..=25).collect::<Vec<_>>());
+    }
+}
@@ -1,8 +1,14 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::Display,
     fs::File,
+    hash::Hash,
     io::{BufRead, BufReader, Read},
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
 };
 
 use thiserror::Error;
@@ -13,29 +19,108 @@ pub enum AocError {
     ParseError,
     #[error("Value Error: {0}")]
     ValueError(String),
+    #[error("Execution Limit: exceeded {0} instructions")]
+    ExecutionLimit(usize),
+    #[error("failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
 }
 
+impl AocError {
+    fn io(path: &Path) -> impl FnOnce(std::io::Error) -> AocError + '_ {
+        move |source| AocError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    One,
+    Two,
+    Both,
+}
+
+impl Part {
+    pub fn one(self) -> bool {
+        matches!(self, Part::One | Part::Both)
+    }
+
+    pub fn two(self) -> bool {
+        matches!(self, Part::Two | Part::Both)
+    }
+}
+
+impl FromStr for Part {
+    type Err = AocError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            "both" => Ok(Part::Both),
+            _ => Err(AocError::ValueError(format!("unknown part: {s}"))),
+        }
+    }
+}
+
+pub fn debug_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var("AOC_DEBUG").is_ok_and(|v| !v.is_empty() && v != "0"))
+}
+
+/// Print to stderr, but only when the `AOC_DEBUG` environment variable is set.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if $crate::util::debug_enabled() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use log;
+
 pub fn get_data_string(path: &Path) -> Result<String> {
-    let file = File::open(path)?;
+    let file = File::open(path).map_err(AocError::io(path))?;
     let mut reader = BufReader::new(file);
     let mut result = String::new();
-    reader.read_to_string(&mut result)?;
+    reader
+        .read_to_string(&mut result)
+        .map_err(AocError::io(path))?;
     Ok(result)
 }
 
+pub fn for_each_line<F>(path: &Path, mut f: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    let file = File::open(path).map_err(AocError::io(path))?;
+    let reader = BufReader::new(file);
+    for line in reader.lines() {
+        f(&line.map_err(AocError::io(path))?)?;
+    }
+    Ok(())
+}
+
 pub fn get_data_fixed_columns<const C: usize>(path: &Path) -> Result<[Vec<usize>; C]> {
-    let file = File::open(path)?;
+    let file = File::open(path).map_err(AocError::io(path))?;
     let reader = BufReader::new(file);
 
     let mut result: [Vec<usize>; C] = vec![Vec::new(); C].try_into().unwrap();
-    for line in reader.lines() {
-        let parsed: Vec<Result<_>> = line?
+    for (line_index, line) in reader.lines().enumerate() {
+        let parsed: Vec<Result<_>> = line
+            .map_err(AocError::io(path))?
             .split_whitespace()
             .map(|val| val.parse::<usize>().map_err(|e| e.into()))
             .collect();
         let parsed: Result<Vec<usize>> = parsed.into_iter().collect();
         let parsed = parsed?;
-        assert_eq!(parsed.len(), C);
+        if parsed.len() != C {
+            return Err(AocError::ParseError)
+                .with_context(|| format!("{}: line {}", path.display(), line_index + 1));
+        }
         for index in 0..C {
             result[index].push(parsed[index]);
         }
@@ -43,24 +128,36 @@ pub fn get_data_fixed_columns<const C: usize>(path: &Path) -> Result<[Vec<usize>
     Ok(result)
 }
 
-pub fn get_data_rows(path: &Path) -> Result<Vec<Vec<usize>>> {
-    let file = File::open(path)?;
+pub fn get_data_rows<T>(path: &Path) -> Result<Vec<Vec<T>>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let file = File::open(path).map_err(AocError::io(path))?;
     let reader = BufReader::new(file);
 
     let mut result = Vec::new();
 
     for line in reader.lines() {
-        let parsed: Vec<Result<_>> = line?
+        let parsed: Vec<Result<_>> = line
+            .map_err(AocError::io(path))?
             .split_whitespace()
-            .map(|val| val.parse::<usize>().map_err(|e| e.into()))
+            .map(|val| val.parse::<T>().map_err(|e| e.into()))
             .collect();
-        let parsed: Result<Vec<usize>> = parsed.into_iter().collect();
+        let parsed: Result<Vec<T>> = parsed.into_iter().collect();
         let parsed = parsed?;
         result.push(parsed);
     }
     Ok(result)
 }
 
+pub fn lines(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .split("\n")
+        .map(|s| s.strip_suffix("\r").unwrap_or(s))
+        .filter(|s| !s.is_empty())
+}
+
 pub fn parse_tiles<T, F>(input: &str, mut parser: F) -> Result<Vec<Vec<T>>, AocError>
 where
     F: FnMut(usize, usize, char) -> Result<T, AocError>,
@@ -85,6 +182,256 @@ where
         .collect()
 }
 
+pub fn render_ppm<F>(width: usize, height: usize, mut pixel_fn: F) -> Vec<u8>
+where
+    F: FnMut(usize, usize) -> [u8; 3],
+{
+    let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+    image.reserve(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            image.extend(pixel_fn(x, y));
+        }
+    }
+    image
+}
+
+// neighbors yields successors with their edge cost, heuristic must never overestimate
+#[allow(dead_code)]
+pub fn astar<N, C, I, H>(
+    start: N,
+    goal: N,
+    mut neighbors: C,
+    mut heuristic: H,
+) -> Option<(Vec<N>, usize)>
+where
+    N: Copy + Eq + Hash,
+    C: FnMut(N) -> I,
+    I: IntoIterator<Item = (N, usize)>,
+    H: FnMut(N) -> usize,
+{
+    let mut best_cost = HashMap::from([(start, 0)]);
+    let mut came_from = HashMap::new();
+    let mut queue = BinaryHeap::from([(Reverse(heuristic(start)), Reverse(0), 0)]);
+    let mut nodes = vec![start];
+
+    while let Some((_, Reverse(cost), index)) = queue.pop() {
+        let node = nodes[index];
+        if best_cost.get(&node).is_some_and(|&c| c < cost) {
+            // already reached this node more cheaply
+            continue;
+        }
+
+        if node == goal {
+            let mut path = vec![node];
+            let mut cur = node;
+            while let Some(&prev) = came_from.get(&cur) {
+                path.push(prev);
+                cur = prev;
+            }
+            path.reverse();
+            return Some((path, cost));
+        }
+
+        for (next, step_cost) in neighbors(node) {
+            let next_cost = cost + step_cost;
+            if best_cost.get(&next).is_some_and(|&c| c <= next_cost) {
+                continue;
+            }
+            best_cost.insert(next, next_cost);
+            came_from.insert(next, node);
+            let estimate = next_cost + heuristic(next);
+            queue.push((Reverse(estimate), Reverse(next_cost), nodes.len()));
+            nodes.push(next);
+        }
+    }
+    None
+}
+
 pub fn is_even(val: usize) -> bool {
     (val & 1) == 0
 }
+
+// one day of the puzzle, each part turned into its answer for the input at `path`
+pub trait Solution {
+    fn day(&self) -> u8;
+    fn part1(&self, path: &Path) -> Result<String>;
+    fn part2(&self, path: &Path) -> Result<String>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub day: u8,
+    pub part: u8,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Mismatch {
+            day,
+            part,
+            expected,
+            actual,
+        } = self;
+        write!(
+            f,
+            "day {day:02} part {part}: expected {expected}, got {actual}"
+        )
+    }
+}
+
+// only the bit of toml the answers file needs: `[dayNN]` sections holding
+// `part1 = "..."` and `part2 = "..."`, plus blank lines and `#` comments
+pub fn parse_answers(input: &str) -> Result<BTreeMap<(u8, u8), String>> {
+    let mut answers = BTreeMap::new();
+    let mut day = None;
+    for (line_index, line) in input.lines().enumerate() {
+        let context = || format!("answers: line {}", line_index + 1);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[') {
+            let number = section
+                .strip_suffix(']')
+                .and_then(|s| s.strip_prefix("day"))
+                .ok_or(AocError::ParseError)
+                .with_context(context)?;
+            day = Some(number.parse::<u8>().with_context(context)?);
+            continue;
+        }
+        let day = day.ok_or(AocError::ParseError).with_context(context)?;
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(AocError::ParseError)
+            .with_context(context)?;
+        let part = match key.trim() {
+            "part1" => 1,
+            "part2" => 2,
+            _ => return Err(AocError::ParseError).with_context(context),
+        };
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .ok_or(AocError::ParseError)
+            .with_context(context)?;
+        if answers.insert((day, part), value.to_string()).is_some() {
+            let s = format!("day {day} part {part} is listed twice");
+            return Err(AocError::ValueError(s)).with_context(context);
+        }
+    }
+    Ok(answers)
+}
+
+// run every part that has a stored answer against its real input
+pub fn verify_all(solutions: &[&dyn Solution]) -> Result<Vec<Mismatch>> {
+    let path = PathBuf::from("./resources/answers.toml");
+    let answers = parse_answers(&get_data_string(&path)?)?;
+    let mut mismatches = Vec::new();
+    for ((day, part), expected) in answers {
+        let input = PathBuf::from(format!("./resources/day{day:02}.txt"));
+        let actual = match solutions.iter().find(|s| s.day() == day) {
+            Some(solution) if part == 1 => solution.part1(&input),
+            Some(solution) => solution.part2(&input),
+            None => Err(AocError::ValueError(format!("no solution for day {day}")).into()),
+        };
+        // a failing day is reported like a wrong answer, so it does not hide the others
+        let actual = actual.unwrap_or_else(|e| format!("error: {e:#}"));
+        if actual != expected {
+            mismatches.push(Mismatch {
+                day,
+                part,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_names_path() {
+        let path = PathBuf::from("./resources/day99.txt");
+        let err = get_data_string(&path).unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("failed to read ./resources/day99.txt: "));
+        assert!(matches!(
+            err.downcast_ref::<AocError>(),
+            Some(AocError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_part() {
+        assert_eq!("1".parse::<Part>().unwrap(), Part::One);
+        assert_eq!("2".parse::<Part>().unwrap(), Part::Two);
+        assert_eq!("both".parse::<Part>().unwrap(), Part::Both);
+        assert!("3".parse::<Part>().is_err());
+        assert!(Part::Both.one() && Part::Both.two());
+        assert!(!Part::Two.one());
+    }
+
+    #[test]
+    fn test_lines_strip_carriage_return() {
+        let input = "a\r\nb\n\r\nc";
+        let lines = lines(input).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_astar_prefers_cheap_detour() {
+        // 0 -> 3 directly is expensive, going over 1 and 2 is cheaper
+        let edges = |n: usize| match n {
+            0 => vec![(1, 1), (3, 10)],
+            1 => vec![(2, 1)],
+            2 => vec![(3, 1)],
+            _ => vec![],
+        };
+        let (path, cost) = astar(0, 3, edges, |_| 0).unwrap();
+        assert_eq!(path, vec![0, 1, 2, 3]);
+        assert_eq!(cost, 3);
+        assert!(astar(3, 0, edges, |_| 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        let input =
+            "# comment\n\n[day01]\npart1 = \"11\"\npart2 = \"31\"\n\n[day17]\npart1 = \"4,6,3\"\n";
+        let answers = parse_answers(input).unwrap();
+        assert_eq!(answers.len(), 3);
+        assert_eq!(answers[&(1, 2)], "31");
+        assert_eq!(answers[&(17, 1)], "4,6,3");
+
+        assert!(parse_answers("part1 = \"1\"").is_err());
+        assert!(parse_answers("[day01]\npart3 = \"1\"").is_err());
+        assert!(parse_answers("[day01]\npart1 = 1").is_err());
+        let err = parse_answers("[day01]\npart1 = \"1\"\n[day01]\npart1 = \"2\"").unwrap_err();
+        assert_eq!(err.to_string(), "answers: line 4");
+    }
+
+    #[test]
+    fn test_fixed_columns_ragged_row() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let name = format!("aoc2024_util_ragged_{}_{nanos}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "1   2\n3\n4   5\n").unwrap();
+        let result = get_data_fixed_columns::<2>(&path);
+        std::fs::remove_file(&path).unwrap();
+        let err = result.unwrap_err();
+        assert!(err.to_string().ends_with("line 2"));
+        assert!(matches!(
+            err.downcast_ref::<AocError>(),
+            Some(AocError::ParseError)
+        ));
+    }
+}